@@ -1,41 +1,230 @@
 //! Utilities for creating cards and decks
 
+use std::{fmt, str::FromStr};
+
+/// The color of a suit.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Color {
+    /// Hearts and diamonds.
+    Red,
+    /// Clubs and spades.
+    Black,
+}
+
+/// The suit of a playing card.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Suit {
+    /// Hearts (♥)
+    Hearts,
+    /// Clubs (♣)
+    Clubs,
+    /// Diamonds (♦)
+    Diamonds,
+    /// Spades (♠)
+    Spades,
+}
+
+impl Suit {
+    /// All suits, in the order hearts, clubs, diamonds, spades.
+    pub const ALL: [Suit; 4] = [Suit::Hearts, Suit::Clubs, Suit::Diamonds, Suit::Spades];
+
+    /// Returns the color of the suit.
+    #[must_use]
+    pub fn color(self) -> Color {
+        match self {
+            Suit::Hearts | Suit::Diamonds => Color::Red,
+            Suit::Clubs | Suit::Spades => Color::Black
+        }
+    }
+
+    /// Returns the position of the suit in `Suit::ALL`, from 0 for hearts to 3 for spades.
+    #[must_use]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the letter of the suit in short card names, such as 'H' for hearts.
+    #[must_use]
+    pub fn letter(self) -> char {
+        ['H', 'C', 'D', 'S'][self.index()]
+    }
+
+    /// Returns the symbol of the suit, such as '♥' for hearts.
+    #[must_use]
+    pub fn symbol(self) -> char {
+        ['♥', '♣', '♦', '♠'][self.index()]
+    }
+}
+
+/// The rank of a playing card.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Rank {
+    /// Ace, the lowest rank
+    Ace = 1,
+    /// Two
+    Two,
+    /// Three
+    Three,
+    /// Four
+    Four,
+    /// Five
+    Five,
+    /// Six
+    Six,
+    /// Seven
+    Seven,
+    /// Eight
+    Eight,
+    /// Nine
+    Nine,
+    /// Ten
+    Ten,
+    /// Jack
+    Jack,
+    /// Queen
+    Queen,
+    /// King, the highest rank
+    King,
+}
+
+impl Rank {
+    /// All ranks, from ace to king.
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
+        Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King
+    ];
+
+    /// Returns the value of the rank, from 1 for an ace to 13 for a king.
+    #[must_use]
+    pub fn value(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the rank with the given value, or `None` if the value is not from 1 to 13.
+    #[must_use]
+    pub fn from_value(value: u8) -> Option<Rank> {
+        Rank::ALL.get(usize::from(value).checked_sub(1)?).copied()
+    }
+
+    /// Returns the name of the rank as printed on a card, such as "A" for an ace or "10" for a ten.
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        ["A", "2", "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K"][usize::from(self.value()) - 1]
+    }
+
+    /// Returns the letter of the rank in short card names, such as 'T' for a ten.
+    #[must_use]
+    pub fn letter(self) -> char {
+        ['A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K'][usize::from(self.value()) - 1]
+    }
+}
+
 /// A struct representing a playing card with a rank and a suit.
-#[derive(Default, Copy, Clone)]
+/// Cards are ordered by rank first, then by suit.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Card {
-    /// The rank of the card, from 1 (Ace) to 13 (King).
-    pub rank: u8,
+    /// The rank of the card, from ace to king.
+    pub rank: Rank,
 
-    /// The suit of the card, represented as an integer where:
-    /// - 1: Hearts
-    /// - 2: Clubs
-    /// - 3: Diamonds
-    /// - 4: Spades
-    pub suit: u8
+    /// The suit of the card.
+    pub suit: Suit
 }
 
-impl PartialEq for Card {
-    fn eq(&self, other: &Self) -> bool {
-        self.rank == other.rank && self.suit == other.suit
+impl Card {
+    /// Returns the color of the card's suit.
+    #[must_use]
+    pub fn color(self) -> Color {
+        self.suit.color()
+    }
+}
+
+/// Writes the short name of the card, a rank character followed by a suit letter, such as "TH" for the ten of hearts.
+/// The alternate form, `{:#}`, writes the card as it is printed on the board instead, such as "10♥".
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}{}", self.rank.symbol(), self.suit.symbol())
+        } else {
+            write!(f, "{}{}", self.rank.letter(), self.suit.letter())
+        }
+    }
+}
+
+/// An error returned when a string does not name a card.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseCardError(String);
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not a card", self.0)
+    }
+}
+
+impl std::error::Error for ParseCardError {}
+
+/// Reads a card from a rank followed by a suit, ignoring case. The rank is one of A, 2 to 9, T or 10, J, Q and K,
+/// and the suit is one of the letters H, C, D and S or the symbols ♥, ♣, ♦ and ♠, so "TH", "As" and "10♥" are all cards.
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(name: &str) -> Result<Card, ParseCardError> {
+        let error = || ParseCardError(name.to_owned());
+        let suit_char = name.chars().next_back().ok_or_else(error)?;
+        let rank_name = &name[..name.len() - suit_char.len_utf8()];
+
+        let suit = Suit::ALL.into_iter()
+            .find(|suit| suit_char.eq_ignore_ascii_case(&suit.letter()) || suit_char == suit.symbol())
+            .ok_or_else(error)?;
+        let rank = Rank::ALL.into_iter()
+            .find(|rank| rank_name.eq_ignore_ascii_case(&rank.letter().to_string()) || rank_name == rank.symbol())
+            .ok_or_else(error)?;
+        Ok(Card{rank, suit})
     }
 }
 
 /// Creates a standard deck of playing cards.
 ///
-/// # Arguments
-///
-/// * `ranks` - The number of ranks per suit in the deck.
-/// * `suits` - The number of suits in the deck.
-///
 /// # Returns
 ///
-/// A vector containing a standard deck of cards with ranks ranging from 1 to `ranks` and suits ranging from 1 to `suits`.
-pub fn new_standard_deck(ranks: u8, suits: u8) -> Vec<Card> {
-        (0..ranks*suits).map(
-            |i|
-            Card {
-                rank: (i % ranks + 1),
-                suit: (i / ranks + 1)
-            }
+/// A vector containing one card of every rank and suit, ordered by suit, then by rank.
+#[must_use]
+pub fn new_standard_deck() -> Vec<Card> {
+        Suit::ALL.into_iter().flat_map(
+            |suit|
+            Rank::ALL.into_iter().map(move |rank| Card {rank, suit})
         ).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{new_standard_deck, Card, ParseCardError, Rank, Suit};
+
+    #[test]
+    fn cards_parse_from_letters_or_symbols_in_any_case() {
+        let ten_of_hearts = Card{rank: Rank::Ten, suit: Suit::Hearts};
+        for name in ["TH", "th", "tH", "10H", "10h", "10♥", "T♥"] {
+            assert_eq!(name.parse(), Ok(ten_of_hearts), "{name}");
+        }
+        assert_eq!("As".parse(), Ok(Card{rank: Rank::Ace, suit: Suit::Spades}));
+        assert_eq!("kD".parse(), Ok(Card{rank: Rank::King, suit: Suit::Diamonds}));
+        assert_eq!("7♣".parse(), Ok(Card{rank: Rank::Seven, suit: Suit::Clubs}));
+    }
+
+    #[test]
+    fn strings_that_name_no_card_are_rejected() {
+        for name in ["", "H", "♥", "1H", "11H", "XH", "TX", "AHH", " AH", "A H"] {
+            assert_eq!(name.parse::<Card>(), Err(ParseCardError(name.to_owned())), "{name}");
+        }
+    }
+
+    #[test]
+    fn cards_parse_back_from_how_they_are_written() {
+        for card in new_standard_deck() {
+            assert_eq!(card.to_string().parse(), Ok(card));
+            assert_eq!(format!("{card:#}").parse(), Ok(card));
+        }
+        let ten_of_hearts = Card{rank: Rank::Ten, suit: Suit::Hearts};
+        assert_eq!(ten_of_hearts.to_string(), "TH");
+        assert_eq!(format!("{ten_of_hearts:#}"), "10♥");
+    }
+}
@@ -1,49 +1,189 @@
 //! Manages the state of the `FreeCell` game
 
+use std::{collections::HashSet, time::{Duration, Instant}};
+
 use circular_buffer::CircularBuffer;
 
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::cards::{new_standard_deck, Card, Rank, Suit};
 
-use crate::cards::{new_standard_deck, Card};
+use self::solver::{Solution, SolverLimits};
 
 /// The total number of ranks in a standard deck of cards.
-const RANKS: u8 = 13;
+pub const RANKS: usize = Rank::ALL.len();
 /// The total number of suits in a standard deck of cards.
-const SUITS: u8 = 4;
+pub const SUITS: usize = Suit::ALL.len();
 /// The total number of cards in a standard deck.
-const DECK_SIZE: usize = RANKS as usize * SUITS as usize;
-
-/// Constant representing the suit of Hearts.
-const HEARTS: u8 = 1;
-/// Constant representing the suit of Clubs.
-const CLUBS: u8 = 2;
-/// Constant representing the suit of Diamonds.
-const DIAMONDS: u8 = 3;
-/// Constant representing the suit of Spades.
-const SPADES: u8 = 4;
-
-/// The number of foundation piles in the game, one for each suit.
-const FOUNDATIONS: usize = SUITS as usize;
+pub const DECK_SIZE: usize = RANKS * SUITS;
+
+/// The number of foundation piles in the game, one for each suit, in the order of `Suit::ALL`.
+pub const FOUNDATIONS: usize = SUITS;
 /// The number of free cells available for storing cards temporarily.
-const FREE_CELLS: usize = 4;
+pub const FREE_CELLS: usize = 4;
 /// The number of tableau piles in the game.
-const TABLEAU_SIZE: usize = 8;
-/// The total size of the game field, including foundations, suits, free cells and tableau piles.
-const FIELD_SIZE: usize = FOUNDATIONS + FREE_CELLS + TABLEAU_SIZE;
+pub const TABLEAU_SIZE: usize = 8;
+/// The total size of the game field, including foundations, free cells and tableau piles.
+/// Stacks are indexed in that order: foundations first, then free cells, then tableau piles.
+pub const FIELD_SIZE: usize = FOUNDATIONS + FREE_CELLS + TABLEAU_SIZE;
 
 /// The maximum number of undo levels the game supports.
 const UNDO_LEVELS: usize = 1000;
 
+/// How long the solver may search for a hint before the game falls back to a simple heuristic.
+const HINT_TIME_BUDGET: Duration = Duration::from_millis(500);
+/// How long a status message stays up before it goes away by itself.
+const STATUS_MESSAGE_TIME: Duration = Duration::from_secs(4);
+/// How long a new status message flashes to catch the player's eye.
+const STATUS_MESSAGE_FLASH_TIME: Duration = Duration::from_millis(400);
+
+/// The highest deal number supported by the Microsoft-compatible deal generator.
+pub const MAX_DEAL_NUMBER: u32 = 1_000_000;
+/// The order of the suits in the deck that Microsoft `FreeCell` shuffles from.
+const MICROSOFT_SUIT_ORDER: [Suit; SUITS] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+/// Identifies a reproducible deal, so that the same starting position can be dealt again.
+/// Deals are ordered with the numbered deals first, by number, then the seeded deals, by seed.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Deal {
+    /// A Microsoft-compatible numbered deal, see `Game::from_deal_number`.
+    Numbered(u32),
+    /// A deal shuffled from a seed, see `Game::from_seed`.
+    Seeded(u64),
+}
+
+impl std::fmt::Display for Deal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Deal::Numbered(deal_number) => write!(f, "Game #{deal_number}"),
+            Deal::Seeded(seed) => write!(f, "Seed {seed}")
+        }
+    }
+}
+
 /// Represents a move in the game, indicating the source and destination stack indices on the game field.
-#[derive(Default, Copy, Clone)]
-struct Move {
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Move {
     /// The index of the stack the card is moved from.
-    from: usize,
+    pub from: usize,
     /// The index of the stack the card is moved to.
-    to: usize
+    pub to: usize,
+    /// The number of cards moved together from the top of the source stack.
+    /// Anything above 1 is a sequence move between tableau columns.
+    pub count: usize
+}
+
+/// An error describing why a set of stacks is not a valid `FreeCell` position.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PositionError {
+    /// The position does not hold exactly one deck of cards. Holds the number of cards found.
+    CardCount(usize),
+    /// A card appears more than once.
+    DuplicateCard(Card),
+    /// A foundation does not hold its own suit in order, starting from the ace. Holds the foundation's field index.
+    Foundation(usize),
+    /// A free cell holds more than one card. Holds the free cell's field index.
+    FreeCell(usize),
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PositionError::CardCount(count) => write!(f, "expected {DECK_SIZE} cards, found {count}"),
+            PositionError::DuplicateCard(card) => write!(f, "{card} appears more than once"),
+            PositionError::Foundation(index) => write!(f, "foundation {} is out of order", index + 1),
+            PositionError::FreeCell(index) => write!(f, "free cell {} holds more than one card", index - FOUNDATIONS + 1)
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+/// A reason why the rules do not allow a move.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// The stack to move from holds no cards.
+    SourceEmpty,
+    /// The move starts and ends on the same stack.
+    SameStack,
+    /// The cards to move are not an ordered sequence of alternating colors and descending ranks.
+    NotASequence,
+    /// Several cards were moved at once to or from somewhere other than the tableau.
+    SequenceOutsideTableau,
+    /// There are not enough free cells and empty columns to move a sequence this long.
+    SequenceTooLong {
+        /// The number of cards in the sequence.
+        count: usize,
+        /// The largest number of cards that can be moved at once.
+        max: usize
+    },
+    /// A card was put on a tableau card of the same color.
+    SameColor {
+        /// The card that was moved.
+        card: Card,
+        /// The card it was put on.
+        onto: Card
+    },
+    /// A card was put on a card it does not follow in rank, or an empty foundation was started with something else than an ace.
+    WrongRank {
+        /// The card that was moved.
+        card: Card,
+        /// The card it was put on, or `None` for an empty foundation.
+        onto: Option<Card>
+    },
+    /// A card was put in a free cell that already holds one.
+    FreeCellOccupied,
+    /// A card was sent to a free cell, but all of them are taken.
+    NoFreeCell,
+    /// A card was put on the foundation of another suit.
+    WrongFoundationSuit {
+        /// The card that was moved.
+        card: Card,
+        /// The suit of the foundation.
+        foundation: Suit
+    },
+    /// Cards were sent off without a destination, but there is no stack they can go to.
+    NoDestination,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MoveError::SourceEmpty => write!(f, "there is no card to move"),
+            MoveError::SameStack => write!(f, "the cards are already there"),
+            MoveError::NotASequence => write!(f, "only ordered sequences can be moved together"),
+            MoveError::SequenceOutsideTableau => write!(f, "only one card can be moved there at a time"),
+            MoveError::SequenceTooLong{count, max} => write!(f, "not enough free space to move {count} cards, only {max}"),
+            MoveError::SameColor{card, onto} => write!(f, "{card:#} cannot go on {onto:#}, they are the same color"),
+            MoveError::WrongRank{card, onto: Some(onto)} => write!(f, "{card:#} cannot go on {onto:#}, the ranks do not follow"),
+            MoveError::WrongRank{card, onto: None} => write!(f, "{card:#} cannot start a foundation, only an ace can"),
+            MoveError::FreeCellOccupied => write!(f, "that free cell is already taken"),
+            MoveError::NoFreeCell => write!(f, "there is no free cell left"),
+            MoveError::WrongFoundationSuit{card, foundation} => write!(f, "{card:#} cannot go on the {} foundation", foundation.symbol()),
+            MoveError::NoDestination => write!(f, "there is nowhere to move the cards")
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// One player command as recorded in the undo history, made of all the moves it played.
+/// Its moves are undone and redone together.
+type Action = Vec<Move>;
+
+/// How the game counts the moves shown in the title bar.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveCounting {
+    /// Every card that changes place counts as a move, so a sequence move of three cards counts three times.
+    #[default]
+    EachCard,
+    /// Every player command counts as one move, however many cards it moves.
+    EachAction,
 }
 
 /// Represents the state of a `FreeCell` game.
+#[derive(Clone)]
 pub struct Game {
     /// The playing field, consisting of stacks of cards.
     field: [Vec<Card>; FIELD_SIZE],
@@ -51,17 +191,51 @@ pub struct Game {
     /// The index of the card the player currently has highlighted
     highlighted_card: usize,
 
+    /// How many cards of the ordered sequence at the top of the highlighted tableau column the cursor takes in, 1 for the top card only.
+    highlighted_depth: usize,
+
     /// The index of the card the player has marked to be moved, if any.
     selected_card_opt: Option<usize>,
 
+    /// How many cards the player picked up with the selection, or 1 to move the longest sequence that fits wherever they go.
+    selected_depth: usize,
+
     /// The circular buffer storing the game's undo history.
-    undo_history: CircularBuffer<UNDO_LEVELS, Move>,
+    undo_history: CircularBuffer<UNDO_LEVELS, Action>,
+
+    /// The circular buffer storing the actions that were undone and can be redone, most recently undone last.
+    redo_history: CircularBuffer<UNDO_LEVELS, Action>,
 
     /// The number of moves made so far in the game.
     move_count: u32,
 
+    /// Indicates whether the game is stuck, see `is_stalemate`. Worked out again whenever the position changes,
+    /// since it replays the whole undo history and is too slow to work out on every frame.
+    stalemate: bool,
+
+    /// How `move_count` counts moves.
+    move_counting: MoveCounting,
+
+    /// Indicates whether cards that are safe to stack are moved to the foundations automatically after every player move.
+    auto_play: bool,
+
     /// Indicates whether the game is in high contrast mode, where each suit is printed in a different color.
     high_contrast: bool,
+
+    /// The deal this game was dealt from, if it can be reproduced.
+    deal: Option<Deal>,
+
+    /// The number of hints the player has asked for so far in the game.
+    hints_used: u32,
+
+    /// The play time of the game up to when the clock was last stopped.
+    elapsed_time: Duration,
+
+    /// When the clock was last started, if it is running.
+    clock_start_opt: Option<Instant>,
+
+    /// A message for the player shown in the status line, such as why their last move was not allowed, and when it was shown, if any.
+    status_message_opt: Option<(String, Instant)>,
 }
 
 impl Game {
@@ -74,21 +248,144 @@ impl Game {
     /// # Returns
     ///
     /// A new `Game` instance.
-    pub fn new(rng: &mut rand::rngs::ThreadRng) -> Game {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Game {
+        let mut deck = new_standard_deck();
+        deck.shuffle(rng);
+        //deck.sort_by_key(|card| card.rank); // for testing
+        //deck.reverse(); // for testing
+        Game::deal_deck(deck, None)
+    }
+
+    /// Creates a new instance of the `FreeCell` game, shuffled reproducibly from a seed.
+    ///
+    /// The deck is shuffled with a `ChaCha8Rng` seeded through `SeedableRng::seed_from_u64`,
+    /// so the same seed always produces the same deal.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed to shuffle the deck with.
+    ///
+    /// # Returns
+    ///
+    /// A new `Game` instance.
+    #[must_use]
+    pub fn from_seed(seed: u64) -> Game {
+        let mut game = Game::new(&mut ChaCha8Rng::seed_from_u64(seed));
+        game.deal = Some(Deal::Seeded(seed));
+        game
+    }
+
+    /// Creates a new instance of the `FreeCell` game from a reproducible deal.
+    ///
+    /// # Arguments
+    ///
+    /// * `deal` - The deal to play.
+    ///
+    /// # Returns
+    ///
+    /// A new `Game` instance.
+    #[must_use]
+    pub fn from_deal(deal: Deal) -> Game {
+        match deal {
+            Deal::Numbered(deal_number) => Game::from_deal_number(deal_number),
+            Deal::Seeded(seed) => Game::from_seed(seed)
+        }
+    }
+
+    /// Creates a new instance of the `FreeCell` game from a numbered deal.
+    ///
+    /// The deck is shuffled with the same linear congruential generator that Microsoft `FreeCell` uses,
+    /// so game #11982 here is the same deal as game #11982 in every other compatible `FreeCell` program.
+    ///
+    /// # Arguments
+    ///
+    /// * `deal_number` - The number of the deal, usually from 1 to `MAX_DEAL_NUMBER`.
+    ///
+    /// # Returns
+    ///
+    /// A new `Game` instance.
+    #[must_use]
+    pub fn from_deal_number(deal_number: u32) -> Game {
+        // Microsoft's deck is ordered by rank first, then by suit
+        let mut deck = new_standard_deck();
+        deck.sort_by_key(|card| (card.rank, MICROSOFT_SUIT_ORDER.iter().position(|&suit| suit == card.suit)));
+
+        // Repeatedly draw a card using the Microsoft C runtime's rand(), filling the gap with the last card of the deck
+        let mut seed = deal_number;
+        let mut shuffled_deck = Vec::with_capacity(DECK_SIZE);
+        while !deck.is_empty() {
+            seed = seed.wrapping_mul(214_013).wrapping_add(2_531_011) & 0x7fff_ffff;
+            let random_index = (seed >> 16) as usize % deck.len();
+            shuffled_deck.push(deck.swap_remove(random_index));
+        }
+
+        Game::deal_deck(shuffled_deck, Some(Deal::Numbered(deal_number)))
+    }
+
+    /// Creates a game from an arbitrary position, with an empty undo history.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The stacks of the position, indexed like `Move::from` and `Move::to`, each with its top card last.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PositionError` if the stacks do not hold exactly one standard deck,
+    /// or if the foundations or free cells hold cards no game could have put there.
+    pub fn from_field(field: [Vec<Card>; FIELD_SIZE]) -> Result<Game, PositionError> {
+        let mut game = Game::deal_deck(Vec::new(), None);
+        game.field = field;
+        game.check_position()?;
+        game.update_stalemate();
+        Ok(game)
+    }
+
+    /// Checks that the field holds exactly one standard deck of cards, that every foundation holds its own suit in order,
+    /// and that every free cell holds at most one card.
+    fn check_position(&self) -> Result<(), PositionError> {
+        let cards: Vec<Card> = self.field.iter().flatten().copied().collect();
+        if cards.len() != DECK_SIZE {
+            return Err(PositionError::CardCount(cards.len()));
+        }
+        for (i, &card) in cards.iter().enumerate() {
+            if cards[..i].contains(&card) {
+                return Err(PositionError::DuplicateCard(card));
+            }
+        }
+        for (i, foundation) in self.field[..FOUNDATIONS].iter().enumerate() {
+            if foundation.iter().enumerate().any(|(height, card)| usize::from(card.rank.value()) != height + 1 || card.suit.index() != i) {
+                return Err(PositionError::Foundation(i));
+            }
+        }
+        if let Some(i) = (FOUNDATIONS..FOUNDATIONS + FREE_CELLS).find(|&i| self.field[i].len() > 1) {
+            return Err(PositionError::FreeCell(i));
+        }
+        Ok(())
+    }
+
+    /// Deals a deck of cards onto the tableau of a fresh board, one card per column from left to right.
+    fn deal_deck(deck: Vec<Card>, deal: Option<Deal>) -> Game {
         let mut game = Game {
             field: core::array::from_fn(|_| Vec::with_capacity(DECK_SIZE)),
             highlighted_card: FOUNDATIONS + FREE_CELLS,
+            highlighted_depth: 1,
             selected_card_opt: None,
+            selected_depth: 1,
             undo_history: CircularBuffer::new(),
+            redo_history: CircularBuffer::new(),
             move_count: 0,
-            high_contrast: false
+            stalemate: false,
+            move_counting: MoveCounting::default(),
+            auto_play: false,
+            high_contrast: false,
+            deal,
+            hints_used: 0,
+            elapsed_time: Duration::ZERO,
+            clock_start_opt: None,
+            status_message_opt: None
         };
 
         // Deal deck onto the board
-        let mut deck = new_standard_deck(RANKS, SUITS);
-        deck.shuffle(rng);
-        //deck.sort_by_key(|card| card.rank); // for testing
-        //deck.reverse(); // for testing
         for (i, card) in deck.into_iter().enumerate() {
             let field_column = FOUNDATIONS + FREE_CELLS + (i % TABLEAU_SIZE);
             game.field[field_column].push(card);
@@ -97,29 +394,208 @@ impl Game {
         game
     }
 
+    /// Returns the deal this game was dealt from, or `None` if it was shuffled by an unseeded random number generator.
+    #[must_use]
+    pub fn deal(&self) -> Option<Deal> {
+        self.deal
+    }
+
+    /// Returns every stack of the field, indexed like `Move::from` and `Move::to`, each with its top card last.
+    #[must_use]
+    pub fn field(&self) -> &[Vec<Card>; FIELD_SIZE] {
+        &self.field
+    }
+
+    /// Returns the foundation piles, one per suit in the order hearts, clubs, diamonds, spades.
+    #[must_use]
+    pub fn foundations(&self) -> &[Vec<Card>] {
+        &self.field[..FOUNDATIONS]
+    }
+
+    /// Returns the free cells, each holding at most one card.
+    #[must_use]
+    pub fn free_cells(&self) -> &[Vec<Card>] {
+        &self.field[FOUNDATIONS..FOUNDATIONS + FREE_CELLS]
+    }
+
+    /// Returns the tableau piles from left to right, each with its top card last.
+    #[must_use]
+    pub fn tableau(&self) -> &[Vec<Card>] {
+        &self.field[FOUNDATIONS + FREE_CELLS..]
+    }
+
+    /// Returns the number of moves made so far, counted as set by `set_move_counting`.
+    #[must_use]
+    pub fn move_count(&self) -> u32 {
+        self.move_count
+    }
+
+    /// Returns the number of hints the player has asked for so far in the game.
+    #[must_use]
+    pub fn hints_used(&self) -> u32 {
+        self.hints_used
+    }
+
+    /// Returns the time the game has been played for, which counts from the first move until the game is won,
+    /// leaving out the time it was paused.
+    #[must_use]
+    pub fn elapsed_time(&self) -> Duration {
+        self.elapsed_time + self.clock_start_opt.map_or(Duration::ZERO, |clock_start| clock_start.elapsed())
+    }
+
+    /// Checks if the clock is running, which it does from the first move until the game is won or paused.
+    #[must_use]
+    pub fn is_clock_running(&self) -> bool {
+        self.clock_start_opt.is_some()
+    }
+
+    /// Stops the clock, for example while the game is paused.
+    pub fn pause_clock(&mut self) {
+        if let Some(clock_start) = self.clock_start_opt.take() {
+            self.elapsed_time += clock_start.elapsed();
+        }
+    }
+
+    /// Starts the clock again after `pause_clock`, unless the game has not started yet or is already won.
+    pub fn resume_clock(&mut self) {
+        if self.elapsed_time > Duration::ZERO && !self.is_won() {
+            self.clock_start_opt.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Returns when the printed game next changes by itself, such as when the clock shows the next second
+    /// or a status message stops flashing, so that it can be printed again at that time.
+    ///
+    /// # Returns
+    ///
+    /// The time of the next change, or `None` if nothing changes until the player does something.
+    #[must_use]
+    pub fn next_tick(&self) -> Option<Instant> {
+        let now = Instant::now();
+        let clock_tick_opt = self.clock_start_opt
+            .map(|_| now + Duration::from_secs(1).saturating_sub(Duration::from_nanos(self.elapsed_time().subsec_nanos().into())));
+        let message_tick_opt = self.status_message_opt.as_ref().map(|&(_, shown)| {
+            let flash_end = shown + STATUS_MESSAGE_FLASH_TIME;
+            if flash_end > now {flash_end} else {shown + STATUS_MESSAGE_TIME}
+        });
+        clock_tick_opt.into_iter().chain(message_tick_opt).min()
+    }
+
+    /// Brings the time-driven parts of the game up to date, removing the status message once it has been up long enough.
+    /// This should be called when the time returned by `next_tick` has come.
+    pub fn tick(&mut self) {
+        if self.status_message_opt.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= STATUS_MESSAGE_TIME) {
+            self.status_message_opt = None;
+        }
+    }
+
+    /// Keeps the clock running while the game is played after a move, and stops it once the game is won.
+    fn update_clock(&mut self) {
+        if self.is_won() {
+            self.pause_clock();
+        } else {
+            self.clock_start_opt.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Sets how the game counts moves. This should be set before the first move is made.
+    pub fn set_move_counting(&mut self, move_counting: MoveCounting) {
+        self.move_counting = move_counting;
+    }
+
     /// Checks if the game has been won.
     ///
     /// # Returns
     ///
     /// `true` if the game has been won, otherwise `false`.
+    #[must_use]
     pub fn is_won(&self) -> bool {
         // Check if all foundation piles are full
-        self.field.iter().take(FOUNDATIONS).all(|stack| stack.len() == RANKS as usize)
+        self.field.iter().take(FOUNDATIONS).all(|stack| stack.len() == RANKS)
     }
     
+    /// Lists every move the player can make in the current position.
+    /// Between tableau columns, each move carries the longest ordered sequence that fits, as `player_try_execute_move` does.
+    ///
+    /// # Returns
+    ///
+    /// The legal moves, ordered by source and then destination stack index.
+    #[must_use]
+    pub fn legal_moves(&self) -> Vec<Move> {
+        (0..FIELD_SIZE).flat_map(|from| (0..FIELD_SIZE).map(move |to| (from, to)))
+            .filter_map(|(from, to)| self.sequence_move_size(from, to).map(|count| Move{from, to, count}))
+            .collect()
+    }
+
+    /// Checks if the game is stuck: it is not won, and every legal move only leads back to a position
+    /// that was already reached earlier in the game. Taking cards back off the foundations is not counted as a way out.
+    ///
+    /// # Returns
+    ///
+    /// `true` if there is no way left to make progress, otherwise `false`.
+    #[must_use]
+    pub fn is_stalemate(&self) -> bool {
+        self.stalemate
+    }
+
+    /// Works out again whether the game is stuck, see `is_stalemate`, after the position has changed.
+    fn update_stalemate(&mut self) {
+        self.stalemate = !self.is_won() && {
+            let visited_positions = self.visited_position_keys();
+            let mut position = self.clone();
+            self.legal_moves().into_iter().filter(|legal_move| legal_move.from >= FOUNDATIONS).all(|legal_move| {
+                position.execute_move(legal_move.from, legal_move.to, legal_move.count);
+                let revisits = visited_positions.contains(&position.position_key());
+                position.execute_move(legal_move.to, legal_move.from, legal_move.count);
+                revisits
+            })
+        };
+    }
+
+    /// Collects the keys of the current position and of every earlier position still in the undo history.
+    fn visited_position_keys(&self) -> HashSet<u64> {
+        let mut position = self.clone();
+        let mut keys = HashSet::from([position.position_key()]);
+        while let Some(action) = position.undo_history.pop_back() {
+            for undone_move in action.iter().rev() {
+                position.execute_move(undone_move.to, undone_move.from, undone_move.count);
+            }
+            keys.insert(position.position_key());
+        }
+        keys
+    }
+
     /// Toggles high contrast mode, making diamonds magenta and spades yellow.
     pub fn toggle_high_contrast(&mut self) {
         self.high_contrast = !self.high_contrast;
     }
 
+    /// Toggles auto-play mode, where cards that are safe to stack are moved to the foundations after every player move.
+    /// Switching it on also moves the cards that are already safe to stack, in one action that can be undone.
+    pub fn toggle_auto_play(&mut self) {
+        self.auto_play = !self.auto_play;
+        if self.auto_play && !self.is_won() {
+            let action = self.stack_safe_cards();
+            self.record_action(action);
+        }
+    }
+
+    /// Takes over the auto-play and high contrast modes of another game, such as the one this game replaces,
+    /// so that they stay the way the player set them.
+    pub fn copy_modes_from(&mut self, other: &Game) {
+        self.auto_play = other.auto_play;
+        self.high_contrast = other.high_contrast;
+    }
+
     /// Moves the cursor to the left on the game field, skipping invalid spots.
     pub fn move_cursor_left(&mut self) {
         // this modulo trick avoids negative numbers on the unsigned int
         self.highlighted_card = (self.highlighted_card + FIELD_SIZE - 1) % FIELD_SIZE;
+        self.highlighted_depth = 1;
 
         match self.selected_card_opt {
             Some(selected_card) => {
-                while !self.move_is_valid(selected_card, self.highlighted_card) && selected_card != self.highlighted_card {
+                while !self.selection_fits(self.highlighted_card) && selected_card != self.highlighted_card {
                     self.move_cursor_left();
                 }
             }
@@ -134,10 +610,11 @@ impl Game {
     /// Moves the cursor to the right on the game field, skipping invalid spots.
     pub fn move_cursor_right(&mut self) {
         self.highlighted_card = (self.highlighted_card + 1) % FIELD_SIZE;
+        self.highlighted_depth = 1;
 
         match self.selected_card_opt {
             Some(selected_card) => {
-                while !self.move_is_valid(selected_card, self.highlighted_card) && selected_card != self.highlighted_card {
+                while !self.selection_fits(self.highlighted_card) && selected_card != self.highlighted_card {
                     self.move_cursor_right();
                 }
             }
@@ -149,102 +626,1032 @@ impl Game {
         }
     }
 
+    /// Moves the cursor up. On a tableau column, the cursor first takes in one more card of the ordered sequence at its top,
+    /// choosing how many cards to pick up, and then moves on to the foundation or free cell above the column.
+    /// While nothing is selected, empty stacks are skipped like when moving left and right, so the cursor stays put.
+    pub fn move_cursor_up(&mut self) {
+        let tableau_start = FOUNDATIONS + FREE_CELLS;
+        if self.highlighted_card < tableau_start {return;}
+        if self.selected_card_opt.is_none() && self.highlighted_depth < self.ordered_sequence_length(self.highlighted_card) {
+            self.highlighted_depth += 1;
+            return;
+        }
+        let above = self.highlighted_card - tableau_start;
+        if self.selected_card_opt.is_some() || !self.field[above].is_empty() {
+            self.highlighted_card = above;
+            self.highlighted_depth = 1;
+        }
+    }
+
+    /// Moves the cursor down. Inside the sequence at the top of a tableau column, the cursor lets go of its lowest card,
+    /// and from a foundation or free cell it moves to the tableau column below.
+    /// While nothing is selected, empty stacks are skipped like when moving left and right, so the cursor stays put.
+    pub fn move_cursor_down(&mut self) {
+        let tableau_start = FOUNDATIONS + FREE_CELLS;
+        if self.highlighted_depth > 1 {
+            self.highlighted_depth -= 1;
+        } else if self.highlighted_card < tableau_start {
+            let below = self.highlighted_card + tableau_start;
+            if self.selected_card_opt.is_some() || !self.field[below].is_empty() {
+                self.highlighted_card = below;
+            }
+        }
+    }
+
+    /// Checks if the selected cards can be moved onto a stack, in the number the player picked up.
+    fn selection_fits(&self, to: usize) -> bool {
+        let Some(selected_card) = self.selected_card_opt else {return false};
+        if self.selected_depth > 1 {
+            self.move_is_valid(selected_card, to, self.selected_depth)
+        } else {
+            self.sequence_move_size(selected_card, to).is_some()
+        }
+    }
+
     /// Quick stacks all visible cards to the foundation piles, recursively.
+    /// All the cards moved are recorded as one action, so a single undo puts them all back.
     pub fn quick_stack_to_foundations(&mut self) {
-        let mut made_move = false;
-
-        'outer: for source_column in 0..self.field.len() {
-            for target_column in 0..FOUNDATIONS {
-                if self.move_is_valid(source_column, target_column) {
-                    self.player_try_execute_move(source_column, target_column);
-                    made_move = true;
-                    break 'outer;
+        let mut action = Vec::new();
+
+        'search: loop {
+            for source_column in 0..self.field.len() {
+                for target_column in 0..FOUNDATIONS {
+                    if let Ok(executed_move) = self.try_execute_move(source_column, target_column) {
+                        action.push(executed_move);
+                        // We made a move, check the new board state for more opportunities
+                        continue 'search;
+                    }
                 }
             }
+            break;
         }
-        // If we made a move, check the new board state for more opportunities
-        if made_move {self.quick_stack_to_foundations()};
+        self.record_action(action);
     }
 
     /// Handles the event where a player clicks space/enter on a card.
+    /// Selecting picks up as many cards as the cursor takes in, see `move_cursor_up`.
     pub fn handle_card_press(&mut self) {
         if self.selected_card_opt.is_none() {
             // Select a card
             self.selected_card_opt = Some(self.highlighted_card);
+            self.selected_depth = self.highlighted_depth;
         } else if Some(self.highlighted_card) == self.selected_card_opt {
             // Deselect a card
             self.selected_card_opt = None;
-        } else {
-            // Execute a move
-            if let Some(selected_card) = self.selected_card_opt {
-                self.player_try_execute_move(selected_card, self.highlighted_card);
+        } else if let Some(selected_card) = self.selected_card_opt {
+            // Execute a move, a failed one explains itself in the status line
+            let _ = if self.selected_depth > 1 {
+                self.player_try_execute_sized_move(Move{from: selected_card, to: self.highlighted_card, count: self.selected_depth})
+            } else {
+                self.player_try_execute_move(selected_card, self.highlighted_card)
+            };
+        }
+    }
+
+    /// Handles the event where a player clicks on a stack, selecting it, deselecting it or moving the selected cards onto it
+    /// just like moving the cursor there and pressing space/enter would. Empty stacks cannot be selected.
+    pub fn handle_stack_press(&mut self, index: usize) {
+        if self.selected_card_opt.is_none() && self.field[index].is_empty() {return;}
+        self.highlight_stack(index);
+        self.handle_card_press();
+    }
+
+    /// Handles the key that moves a card to the foundations. The selected card, or else the highlighted one,
+    /// is moved to the foundation of its suit, or the status line shows why it cannot go there.
+    pub fn handle_foundation_press(&mut self) {
+        let from = self.selected_card_opt.unwrap_or(self.highlighted_card);
+        let Some(&card) = self.field[from].last() else {return};
+        // A failed move explains itself in the status line
+        if self.player_try_execute_move(from, card.suit.index()).is_ok() {
+            self.highlight_stack(card.suit.index());
+        }
+    }
+
+    /// Moves the cursor to a stack, for example the one the player is dragging cards over.
+    pub fn highlight_stack(&mut self, index: usize) {
+        self.highlighted_card = index;
+        self.highlighted_depth = 1;
+    }
+
+    /// Returns the index of the stack the player has selected to move cards from, if any.
+    #[must_use]
+    pub fn selected_stack(&self) -> Option<usize> {
+        self.selected_card_opt
+    }
+
+    /// Sends the top card of a stack to its foundation, or to the first empty free cell if it cannot go to the foundation yet.
+    /// Cards already in a free cell are only sent to their foundation, and cards on a foundation are not sent anywhere.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why the card cannot be sent anywhere, which is also shown in the status line.
+    pub fn send_to_foundation_or_free_cell(&mut self, from: usize) -> Result<(), MoveError> {
+        if from < FOUNDATIONS {return self.report_move_error(MoveError::NoDestination);}
+        let Some(&card) = self.field[from].last() else {return self.report_move_error(MoveError::SourceEmpty)};
+        let foundation = card.suit.index();
+        let free_cells = FOUNDATIONS..FOUNDATIONS + FREE_CELLS;
+        if self.move_is_valid(from, foundation, 1) || free_cells.contains(&from) {
+            return self.player_try_execute_move(from, foundation);
+        }
+        if let Some(free_cell) = free_cells.clone().find(|&i| self.field[i].is_empty()) {
+            return self.player_try_execute_move(from, free_cell);
+        }
+        self.report_move_error(MoveError::NoFreeCell)
+    }
+
+    /// Sends the selected cards, or else the highlighted ones, to the best stack that takes them, in one undoable action.
+    /// See `smart_send_move` for how the destination is picked.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` if there is no card to send or nowhere to send it, which is also shown in the status line.
+    pub fn smart_send(&mut self) -> Result<(), MoveError> {
+        let (from, depth) = match self.selected_card_opt {
+            Some(selected_card) => (selected_card, self.selected_depth),
+            None => (self.highlighted_card, self.highlighted_depth)
+        };
+        if self.field[from].is_empty() {return self.report_move_error(MoveError::SourceEmpty);}
+        match self.smart_send_move(from, depth) {
+            Some(send_move) => self.player_try_execute_sized_move(send_move),
+            None => self.report_move_error(MoveError::NoDestination)
+        }
+    }
+
+    /// Picks where to send cards from a stack: the foundation first, then a tableau card to build on, then an empty column,
+    /// and a free cell last, taking the leftmost stack when several are as good. The cards sent are the number picked up
+    /// if that is more than 1, otherwise the longest ordered sequence that fits, like a player move.
+    /// Moves that get nowhere, from one free cell to another or a whole column into an empty one, are left out,
+    /// so the cards sent into an empty column leave at least one card behind.
+    ///
+    /// # Returns
+    ///
+    /// The move to make, or `None` if the cards cannot go anywhere.
+    fn smart_send_move(&self, from: usize, depth: usize) -> Option<Move> {
+        if from < FOUNDATIONS {return None;}
+        let free_cells = FOUNDATIONS..FOUNDATIONS + FREE_CELLS;
+        let tableau = FOUNDATIONS + FREE_CELLS..FIELD_SIZE;
+        let gets_somewhere = |to: usize, count: usize| {
+            let between_free_cells = free_cells.contains(&from) && free_cells.contains(&to);
+            let whole_column_to_empty = tableau.contains(&from) && tableau.contains(&to)
+                && self.field[to].is_empty() && count == self.field[from].len();
+            !between_free_cells && !whole_column_to_empty
+        };
+        (0..FIELD_SIZE)
+            .filter_map(|to| {
+                let fits = |&count: &usize| self.move_is_valid(from, to, count) && gets_somewhere(to, count);
+                let count = if depth > 1 {
+                    Some(depth).filter(fits)
+                } else {
+                    (1..=self.ordered_sequence_length(from)).rev().find(fits)
+                };
+                count.map(|count| Move{from, to, count})
+            })
+            .min_by_key(|send_move| {
+                if send_move.to < FOUNDATIONS {
+                    0
+                } else if free_cells.contains(&send_move.to) {
+                    3
+                } else if self.field[send_move.to].is_empty() {
+                    2
+                } else {
+                    1
+                }
+            })
+    }
+
+    /// Suggests a good next move by selecting its source stack and highlighting its destination stack.
+    /// The suggestion comes from the solver if it finds a win in time, otherwise from a simple heuristic.
+    pub fn show_hint(&mut self) {
+        let limits = SolverLimits {
+            time_budget: Some(HINT_TIME_BUDGET),
+            ..SolverLimits::default()
+        };
+        let solver_hint = match solver::solve(self, &limits) {
+            // Only suggest solver moves that pressing on the two stacks would play out the same way
+            Solution::Solvable(moves) => moves.first().copied()
+                .filter(|first_move| self.sequence_move_size(first_move.from, first_move.to) == Some(first_move.count)),
+            Solution::Unsolvable | Solution::Unknown => None
+        };
+        if let Some(hint) = solver_hint.or_else(|| self.heuristic_hint()) {
+            self.selected_card_opt = Some(hint.from);
+            self.selected_depth = 1;
+            self.highlight_stack(hint.to);
+            self.hints_used += 1;
+        }
+    }
+
+    /// Picks a reasonable next move without searching ahead. In order of preference, these are moves to the foundations,
+    /// moves that dig out an ace, moves onto other cards of the tableau, moves into empty columns and moves into free cells.
+    fn heuristic_hint(&self) -> Option<Move> {
+        let free_cells = FOUNDATIONS..FOUNDATIONS + FREE_CELLS;
+        let mut candidates = Vec::new();
+        for from in FOUNDATIONS..FIELD_SIZE {
+            for to in 0..FIELD_SIZE {
+                let Some(count) = self.sequence_move_size(from, to) else {continue};
+                let cards_left_behind = &self.field[from][..self.field[from].len() - count];
+                // Shuffling cards between free cells, or a whole column into an empty one, gets nowhere
+                if free_cells.contains(&from) && free_cells.contains(&to) {continue;}
+                if cards_left_behind.is_empty() && self.field[to].is_empty() && !free_cells.contains(&to) {continue;}
+
+                let cards_above_ace = cards_left_behind.iter().rev().position(|card| card.rank == Rank::Ace);
+                let preference = if to < FOUNDATIONS {
+                    (0, 0)
+                } else if let Some(cards_above_ace) = cards_above_ace {
+                    (1, cards_above_ace)
+                } else if free_cells.contains(&to) {
+                    (4, 0)
+                } else if self.field[to].is_empty() {
+                    (3, 0)
+                } else {
+                    (2, 0)
+                };
+                candidates.push((preference, Move{from, to, count}));
+            }
+        }
+        candidates.into_iter().min_by_key(|&(preference, _)| preference).map(|(_, hint)| hint)
+    }
+
+    /// Executes a player move if it is valid, or shows why it is not in the status line.
+    /// Between tableau columns, the longest ordered sequence that fits on the destination is moved at once.
+    /// In auto-play mode, the cards it makes safe to stack follow it to the foundations as part of the same action.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why the move is not allowed, in which case nothing is moved.
+    pub fn player_try_execute_move(&mut self, from: usize, to: usize) -> Result<(), MoveError> {
+        match self.player_move_size(from, to) {
+            Ok(count) => self.player_try_execute_sized_move(Move{from, to, count}),
+            Err(error) => self.report_move_error(error)
+        }
+    }
+
+    /// Executes a player move of exactly the number of cards given, such as a part of a sequence the player picked up,
+    /// if it is valid, or shows why it is not in the status line. Auto-play follows it like in `player_try_execute_move`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why the move is not allowed, in which case nothing is moved.
+    fn player_try_execute_sized_move(&mut self, game_move: Move) -> Result<(), MoveError> {
+        if let Err(error) = self.check_move(game_move.from, game_move.to, game_move.count) {
+            return self.report_move_error(error);
+        }
+        self.execute_move(game_move.from, game_move.to, game_move.count);
+        let mut action = vec![game_move];
+        if self.auto_play {
+            action.extend(self.stack_safe_cards());
+        }
+        self.record_action(action);
+        Ok(())
+    }
+
+    /// Shows why a player move is not allowed in the status line.
+    ///
+    /// # Errors
+    ///
+    /// Always returns the `MoveError` it was given, so it can be passed on to the caller.
+    fn report_move_error(&mut self, error: MoveError) -> Result<(), MoveError> {
+        self.set_status_message(error.to_string());
+        Err(error)
+    }
+
+    /// Returns the message shown in the status line, if any.
+    #[must_use]
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message_opt.as_ref().map(|(message, _)| message.as_str())
+    }
+
+    /// Shows a message for the player in the status line, until it is cleared with `clear_status_message`
+    /// or goes away by itself a few seconds later, see `tick`.
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message_opt = Some((message, Instant::now()));
+    }
+
+    /// Removes the message from the status line.
+    pub fn clear_status_message(&mut self) {
+        self.status_message_opt = None;
+    }
+
+    /// Moves every card that is safe to stack to the foundations, until none are left.
+    ///
+    /// # Returns
+    ///
+    /// The moves that were executed, which are not recorded in the undo history.
+    fn stack_safe_cards(&mut self) -> Vec<Move> {
+        let mut moves = Vec::new();
+
+        'search: loop {
+            for source_column in FOUNDATIONS..FIELD_SIZE {
+                let Some(&card) = self.field[source_column].last() else {continue};
+                if self.is_safe_to_stack(card) {
+                    if let Ok(executed_move) = self.try_execute_move(source_column, card.suit.index()) {
+                        moves.push(executed_move);
+                        continue 'search;
+                    }
+                }
             }
+            break;
         }
+        moves
+    }
+
+    /// Plays a move exactly as given, recording it in the undo history as one action so `perform_undo` takes it back.
+    /// Unlike `player_try_execute_move`, the number of cards to move is taken from the move rather than chosen automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why the move is not allowed, in which case nothing is moved.
+    pub fn apply_move(&mut self, game_move: Move) -> Result<(), MoveError> {
+        self.check_move(game_move.from, game_move.to, game_move.count)?;
+        self.execute_move(game_move.from, game_move.to, game_move.count);
+        self.record_action(vec![game_move]);
+        Ok(())
+    }
+
+    /// Executes a move if it is valid, without recording it in the undo history.
+    ///
+    /// # Returns
+    ///
+    /// The move that was executed, or a `MoveError` explaining why no move between the two stacks is valid.
+    fn try_execute_move(&mut self, from: usize, to: usize) -> Result<Move, MoveError> {
+        let count = self.player_move_size(from, to)?;
+        self.execute_move(from, to, count);
+        Ok(Move{from, to, count})
+    }
+
+    /// Records the moves made by one player command as a single action in the undo history, and counts them.
+    fn record_action(&mut self, action: Action) {
+        if action.is_empty() {return;}
+        self.move_count += self.counted_moves(&action);
+        self.undo_history.push_back(action);
+        // A new action starts a new line of history, so the undone actions can no longer be redone
+        self.redo_history.clear();
+        self.update_clock();
+        self.update_stalemate();
     }
 
-    /// Executes a player move if it is valid.
-    pub fn player_try_execute_move(&mut self, from: usize, to: usize) {
-        if self.move_is_valid(from, to) {
-            // Execute move, add to undo history
-            self.execute_move(from, to);
-            self.move_count += 1;
-            self.undo_history.push_back(Move{from, to});
+    /// Returns how many moves an action adds to `move_count`.
+    fn counted_moves(&self, action: &[Move]) -> u32 {
+        match self.move_counting {
+            #[allow(clippy::cast_possible_truncation)]
+            MoveCounting::EachCard => action.iter().map(|action_move| action_move.count as u32).sum(),
+            MoveCounting::EachAction => 1
         }
     }
 
-    /// Undoes the last move made by the player.
+    /// Undoes the last action made by the player, including every move it made.
     /// Can be used multiple times to travel back in the game's history.
     pub fn perform_undo(&mut self) {
-        let last_move_opt = self.undo_history.pop_back();
-        if let Some(last_move) = last_move_opt {
-            self.execute_move(last_move.to, last_move.from);
-            self.move_count -= 1;
+        let last_action_opt = self.undo_history.pop_back();
+        if let Some(last_action) = last_action_opt {
+            for undone_move in last_action.iter().rev() {
+                self.execute_move(undone_move.to, undone_move.from, undone_move.count);
+            }
+            self.move_count = self.move_count.saturating_sub(self.counted_moves(&last_action));
+            self.redo_history.push_back(last_action);
+            self.update_clock();
+            self.update_stalemate();
         } // Else history is empty
     }
 
+    /// Redoes the last action undone by the player, including every move it made.
+    /// Can be used multiple times to travel forward again through undone actions, until a new action is made.
+    pub fn perform_redo(&mut self) {
+        let undone_action_opt = self.redo_history.pop_back();
+        if let Some(undone_action) = undone_action_opt {
+            for redone_move in &undone_action {
+                self.execute_move(redone_move.from, redone_move.to, redone_move.count);
+            }
+            self.move_count += self.counted_moves(&undone_action);
+            self.undo_history.push_back(undone_action);
+            self.update_clock();
+            self.update_stalemate();
+        } // Else nothing has been undone
+    }
+
+    /// Checks if a card can be moved to its foundation without ever being needed on the tableau again,
+    /// which is the case once both cards of the opposite color and one rank lower are on the foundations.
+    fn is_safe_to_stack(&self, card: Card) -> bool {
+        Suit::ALL.into_iter().filter(|suit| suit.color() != card.color())
+            .all(|suit| self.field[suit.index()].len() + 1 >= usize::from(card.rank.value()))
+    }
+
+    /// Computes a hash of the position that ignores the order of the free cells and of the tableau columns,
+    /// since positions that only differ in those orders play out exactly the same.
+    fn position_key(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut free_cells: Vec<&Vec<Card>> = self.field[FOUNDATIONS..FOUNDATIONS + FREE_CELLS].iter().collect();
+        free_cells.sort_unstable();
+        let mut tableau: Vec<&Vec<Card>> = self.field[FOUNDATIONS + FREE_CELLS..].iter().collect();
+        tableau.sort_unstable();
+
+        let mut hasher = std::hash::DefaultHasher::new();
+        self.field[..FOUNDATIONS].iter().map(Vec::len).collect::<Vec<usize>>().hash(&mut hasher);
+        free_cells.hash(&mut hasher);
+        tableau.hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Checks if two cards are of opposite colors.
     fn are_opposite_colors(card1: Card, card2: Card) -> bool {
-        if card1.suit == HEARTS || card1.suit == DIAMONDS {return card2.suit == SPADES || card2.suit == CLUBS};
-        if card1.suit == SPADES || card1.suit == CLUBS {return card2.suit == HEARTS || card2.suit == DIAMONDS};
-        false
+        card1.color() != card2.color()
+    }
+
+    /// Counts the cards at the top of a stack that form an ordered sequence of alternating colors and descending ranks.
+    fn ordered_sequence_length(&self, column: usize) -> usize {
+        let stack = &self.field[column];
+        if stack.is_empty() {return 0;}
+        if column < FOUNDATIONS + FREE_CELLS {return 1;}
+        1 + stack.windows(2).rev()
+            .take_while(|pair| pair[1].rank.value() + 1 == pair[0].rank.value() && Game::are_opposite_colors(pair[0], pair[1]))
+            .count()
+    }
+
+    /// Returns the largest number of cards that can be moved as one sequence onto the given stack,
+    /// which is (free cells + 1) × 2^(empty tableau columns), not counting the destination itself.
+    fn max_sequence_move_size(&self, to: usize) -> usize {
+        let empty_free_cells = self.field[FOUNDATIONS..FOUNDATIONS + FREE_CELLS].iter().filter(|stack| stack.is_empty()).count();
+        let empty_columns = (FOUNDATIONS + FREE_CELLS..FIELD_SIZE).filter(|&i| i != to && self.field[i].is_empty()).count();
+        (empty_free_cells + 1) << empty_columns
+    }
+
+    /// Finds the number of cards to move from one stack to another, preferring the longest valid sequence.
+    ///
+    /// # Returns
+    ///
+    /// The number of cards to move, or `None` if no move between the two stacks is valid.
+    fn sequence_move_size(&self, from: usize, to: usize) -> Option<usize> {
+        let longest = self.ordered_sequence_length(from).min(self.max_sequence_move_size(to));
+        (1..=longest).rev().find(|&count| self.move_is_valid(from, to, count))
+    }
+
+    /// Finds the number of cards a player move from one stack to another carries, like `sequence_move_size`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why no move between the two stacks is valid.
+    fn player_move_size(&self, from: usize, to: usize) -> Result<usize, MoveError> {
+        if let Some(count) = self.sequence_move_size(from, to) {return Ok(count);}
+        // Explain the failure with the sequence whose bottom card would fit on the destination column, otherwise with the top card
+        let tableau = FOUNDATIONS + FREE_CELLS..FIELD_SIZE;
+        let fitting_count = self.field[to].last().filter(|_| tableau.contains(&to)).and_then(|&to_top_card| {
+            (1..=self.ordered_sequence_length(from))
+                .find(|&count| self.field[from][self.field[from].len() - count].rank.value() + 1 == to_top_card.rank.value())
+        });
+        self.check_move(from, to, fitting_count.unwrap_or(1)).and(Err(MoveError::NotASequence))
     }
 
-    /// Checks if a move from one position to another is valid.
-    fn move_is_valid(&self, from: usize, to: usize) -> bool {
-        if from == to {return false;};
-        let from_top_card = self.field[from].last().copied().unwrap_or_default();
-        let to_top_card = self.field[to].last().copied().unwrap_or_default();
+    /// Checks if moving a number of cards from the top of one stack to another is valid.
+    fn move_is_valid(&self, from: usize, to: usize, count: usize) -> bool {
+        self.check_move(from, to, count).is_ok()
+    }
+
+    /// Checks if moving a number of cards from the top of one stack to another is valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MoveError` explaining why the move is not valid.
+    fn check_move(&self, from: usize, to: usize, count: usize) -> Result<(), MoveError> {
+        if from == to {return Err(MoveError::SameStack);}
+        if self.field[from].is_empty() {return Err(MoveError::SourceEmpty);}
+        if count == 0 || count > self.field[from].len() {return Err(MoveError::NotASequence);}
+        if count > 1 {
+            // Sequence move case
+            let tableau = FOUNDATIONS + FREE_CELLS..FIELD_SIZE;
+            if !tableau.contains(&from) || !tableau.contains(&to) {return Err(MoveError::SequenceOutsideTableau);}
+            if count > self.ordered_sequence_length(from) {return Err(MoveError::NotASequence);}
+            let max = self.max_sequence_move_size(to);
+            if count > max {return Err(MoveError::SequenceTooLong{count, max});}
+        }
+        let from_card = self.field[from][self.field[from].len() - count];
+        let to_top_card_opt = self.field[to].last().copied();
         if to < FOUNDATIONS {
             // Foundation case
-            if to_top_card.rank != 0 {
-                    return from_top_card.rank == to_top_card.rank + 1 && from_top_card.suit == to_top_card.suit;
-            }
-            return from_top_card.rank == 1 && to == (from_top_card.suit - 1) as usize;
+            let foundation = Suit::ALL[to];
+            if from_card.suit != foundation {return Err(MoveError::WrongFoundationSuit{card: from_card, foundation});}
+            let follows = match to_top_card_opt {
+                Some(to_top_card) => from_card.rank.value() == to_top_card.rank.value() + 1,
+                None => from_card.rank == Rank::Ace
+            };
+            if !follows {return Err(MoveError::WrongRank{card: from_card, onto: to_top_card_opt});}
         } else if to < FOUNDATIONS + FREE_CELLS {
             // Free cell case
-            return to_top_card.rank == 0;
-        } else if to < FOUNDATIONS + FREE_CELLS + TABLEAU_SIZE {
+            if to_top_card_opt.is_some() {return Err(MoveError::FreeCellOccupied);}
+        } else if let Some(to_top_card) = to_top_card_opt {
             // Tableau case
-            if to_top_card.rank != 0 {
-                return from_top_card.rank == to_top_card.rank - 1 && Game::are_opposite_colors(from_top_card, to_top_card);
+            if from_card.rank.value() + 1 != to_top_card.rank.value() {
+                return Err(MoveError::WrongRank{card: from_card, onto: Some(to_top_card)});
+            }
+            if !Game::are_opposite_colors(from_card, to_top_card) {
+                return Err(MoveError::SameColor{card: from_card, onto: to_top_card});
             }
-            return true;
         }
-        false
+        Ok(())
     }
 
-    /// Executes a move from one position to another, not checking if it follows the rules.
+    /// Executes a move of a number of cards from one position to another, not checking if it follows the rules.
     /// To try executing a move in a way that fails if the move does not follow the rules, use `player_try_execute_move`.
-    fn execute_move (&mut self, from: usize, to: usize) {
+    fn execute_move (&mut self, from: usize, to: usize, count: usize) {
         // Execute the move
-        // Move "from" card to "to" column
-        let from_card_opt = self.field[from].last();
-        if let Some(&from_card) = from_card_opt {
-            self.field[from].pop();
-            self.field[to].push(from_card);
-        }
+        // Move the top "count" cards of the "from" column onto the "to" column, keeping their order
+        let split_index = self.field[from].len().saturating_sub(count);
+        let moved_cards = self.field[from].split_off(split_index);
+        self.field[to].extend(moved_cards);
         self.selected_card_opt = None;
+        self.highlighted_depth = 1;
     }
 }
 
-mod print;
+pub mod board;
+pub mod notation;
+#[cfg(feature = "tui")]
+pub mod print;
+pub mod save;
+pub mod solver;
+pub mod stats;
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Deal, Game, Move, MoveCounting, MoveError, STATUS_MESSAGE_FLASH_TIME, STATUS_MESSAGE_TIME};
+    use crate::cards::Suit;
+
+    #[test]
+    fn deal_numbers_match_microsoft_freecell() {
+        assert_eq!(Game::from_deal_number(1).board_string(), "\
+            Foundations: H-0 C-0 D-0 S-0\n\
+            Freecells: - - - -\n\
+            : JD KD 2S 4C 3S 6D 6S\n\
+            : 2D KC KS 5C TD 8S 9C\n\
+            : 9H 9S 9D TS 4S 8D 2H\n\
+            : JC 5S QD QH TH QS 6H\n\
+            : 5D AD JS 4H 8H 6C\n\
+            : 7H QC AS AC 2C 3D\n\
+            : 7C KH AH 4D JH 8C\n\
+            : 5H 3H 3C 7S 7D TC\n");
+        assert_eq!(Game::from_deal_number(11982).board_string(), "\
+            Foundations: H-0 C-0 D-0 S-0\n\
+            Freecells: - - - -\n\
+            : AH 3D KD JC 6C JD KC\n\
+            : AS 3H 6H 5D 2C 7D 8D\n\
+            : 4H QS 5S 5C TH 8H 2S\n\
+            : AC QC 4D 8C QH 9C 3S\n\
+            : 2D 8S 9H 9D 6D 2H\n\
+            : 6S 7H JH TD TC QD\n\
+            : TS AD 9S KH 4S 4C\n\
+            : JS KS 3C 7C 7S 5H\n");
+    }
+
+    #[test]
+    fn seeds_give_the_same_deal() {
+        // Seeds are shown to players and kept in the statistics, so a seed must never start dealing a different game
+        assert_eq!(Game::from_seed(1).board_string(), "\
+            Foundations: H-0 C-0 D-0 S-0\n\
+            Freecells: - - - -\n\
+            : 3S 7S KC 9H TH 4D JH\n\
+            : JC 8H TC 6D 8D 7C JS\n\
+            : 3C 4S 2D KD 4H AS 8C\n\
+            : 3H 4C 6H 9S 5D KH 3D\n\
+            : 5H 8S AH AD 9C QD\n\
+            : AC 5C QS JD 2H TS\n\
+            : QC 2C 7D TD 9D 6C\n\
+            : 5S 2S QH 6S 7H KS\n");
+        assert_eq!(Game::from_seed(1).deal(), Some(Deal::Seeded(1)));
+        assert_eq!(Game::from_seed(2_168_649_807).board_string(), "\
+            Foundations: H-0 C-0 D-0 S-0\n\
+            Freecells: - - - -\n\
+            : 6D 2H 2S AC QD 5D 5C\n\
+            : 7S 3S 2D QS TD TC TH\n\
+            : KC 4S KS 7C 6S 2C 3C\n\
+            : JH 5S QH 8H 6C 8D 7H\n\
+            : 3D AD AH 8S 4C 4D\n\
+            : KD QC 9H 8C KH 9C\n\
+            : 9S 9D 4H JC 5H JD\n\
+            : JS 3H 6H 7D TS AS\n");
+    }
+
+    #[test]
+    fn redo_brings_back_undone_moves_until_a_new_move() {
+        let mut game = Game::from_deal_number(1);
+        game.replay("1a 2b").unwrap();
+        let after_both = game.board_string();
+        game.perform_undo();
+        game.perform_undo();
+        game.perform_redo();
+        assert_eq!(game.move_notation().unwrap(), "1a");
+        game.perform_redo();
+        assert_eq!(game.board_string(), after_both);
+        assert_eq!(game.move_count(), 2);
+
+        // A new move starts a new line of history, leaving nothing to redo
+        game.perform_undo();
+        game.replay("3b").unwrap();
+        let after_new_move = game.board_string();
+        game.perform_redo();
+        assert_eq!(game.board_string(), after_new_move);
+        assert_eq!(game.move_notation().unwrap(), "1a 3b");
+    }
+
+    /// A position one step from the end, where every card left can go to the foundations.
+    fn nearly_won_board() -> Game {
+        Game::from_board_string("\
+            Foundations: H-9 C-9 D-9 S-9\n\
+            : KS QH JC TD\n\
+            : KH QS JD\n\
+            : KD QC JH TC\n\
+            : KC QD JS TH\n\
+            : TS\n").unwrap()
+    }
+
+    #[test]
+    fn quick_stack_is_one_undo_step() {
+        let start = nearly_won_board();
+        let mut game = start.clone();
+        game.quick_stack_to_foundations();
+        assert!(game.is_won());
+        assert_eq!(game.undo_history.len(), 1);
+        game.perform_undo();
+        assert_eq!(game.board_string(), start.board_string());
+        assert_eq!(game.move_count(), 0);
+    }
+
+    #[test]
+    fn moves_are_counted_per_card_or_per_action() {
+        let mut game = nearly_won_board();
+        game.quick_stack_to_foundations();
+        assert_eq!(game.move_count(), 16);
+
+        let mut game = nearly_won_board();
+        game.set_move_counting(MoveCounting::EachAction);
+        game.quick_stack_to_foundations();
+        assert_eq!(game.move_count(), 1);
+        game.perform_undo();
+        assert_eq!(game.move_count(), 0);
+        game.perform_redo();
+        assert_eq!(game.move_count(), 1);
+    }
+
+    #[test]
+    fn deal_starts_with_every_card_free_to_go_to_the_free_cells() {
+        // No top card of deal #1 fits on another, so each of the 8 can only go to one of the 4 free cells
+        let legal_moves = Game::from_deal_number(1).legal_moves();
+        assert_eq!(legal_moves.len(), 32);
+        assert!(legal_moves.iter().all(|legal_move| (4..8).contains(&legal_move.to) && legal_move.count == 1));
+    }
+
+    /// A position with kings in the free cells and hearts from 2 to 9 on top of the columns, with the aces buried
+    /// and only 9H sitting on a card it could go back to.
+    /// The free cell given holds the last king unless it is "-", in which case the king starts the first column.
+    fn stuck_board(last_free_cell: &str) -> Game {
+        let first_column = if last_free_cell == "-" {"KS AH"} else {"AH"};
+        Game::from_board_string(&format!("\
+            Foundations: H-0 C-0 D-0 S-0\n\
+            Freecells: KH KC KD {last_free_cell}\n\
+            : {first_column} TH JH QH AC 2H\n\
+            : 2C 3C 4C 5C 6C 3H\n\
+            : 7C 8C 9C TC JC 4H\n\
+            : QC AD 2D 3D 4D 5H\n\
+            : 5D 6D 7D 8D 9D 6H\n\
+            : TD JD QD AS 2S 7H\n\
+            : 3S 4S 5S 6S 7S 8H\n\
+            : 8S 9S JS QS TS 9H\n")).unwrap()
+    }
+
+    #[test]
+    fn game_without_moves_is_stuck() {
+        let game = stuck_board("KS");
+        assert!(game.legal_moves().is_empty());
+        assert!(game.is_stalemate());
+        assert!(!Game::from_deal_number(1).is_stalemate());
+    }
+
+    #[test]
+    fn game_whose_moves_all_lead_back_is_stuck() {
+        let mut game = stuck_board("-");
+        assert!(!game.is_stalemate());
+
+        // With the last free cell taken, the only move left puts the card back where it came from
+        game.player_try_execute_move(15, 7).unwrap();
+        assert_eq!(game.legal_moves(), vec![Move{from: 7, to: 15, count: 1}]);
+        assert!(game.is_stalemate());
+        game.perform_undo();
+        assert!(!game.is_stalemate());
+        game.perform_redo();
+        assert!(game.is_stalemate());
+    }
+
+    /// A position with the run QH JS TH 9S 8H 7S on the first column and KC on the second to move it onto.
+    /// The last columns hold the cards that fill the free cells and empty columns in the tests of the supermove limit.
+    fn supermove_board(free_cells: &str, last_columns: &str) -> Game {
+        Game::from_board_string(&format!("\
+            Foundations: H-6 C-6 D-6 S-6\n\
+            Freecells: {free_cells}\n\
+            : KS QH JS TH 9S 8H 7S\n\
+            : KC\n\
+            : KH QS JH TS 9H 8S 7H\n\
+            : KD QC JD TC 9D 8C 7D\n\
+            {last_columns}")).unwrap()
+    }
+
+    #[test]
+    fn supermove_limit_counts_free_cells_and_empty_columns() {
+        // (4 + 1) × 2^0
+        let game = supermove_board("- - - -", ": QD JC TD\n: 7C\n: 8D\n: 9C\n");
+        assert_eq!(game.max_sequence_move_size(9), 5);
+        assert_eq!(game.check_move(8, 9, 6), Err(MoveError::SequenceTooLong{count: 6, max: 5}));
+        assert_eq!(game.player_move_size(8, 9), Err(MoveError::SequenceTooLong{count: 6, max: 5}));
+
+        // (2 + 1) × 2^1
+        let mut game = supermove_board("7C 8D - -", ": QD JC\n: 9C\n: TD\n");
+        assert_eq!(game.max_sequence_move_size(9), 6);
+        let start = game.clone();
+        game.player_try_execute_move(8, 9).unwrap();
+        assert_eq!(game.field[9].len(), 7);
+        assert_eq!(game.move_count(), 6);
+
+        // One undo puts the whole sequence back
+        game.perform_undo();
+        assert_eq!(game.field, start.field);
+        assert_eq!(game.move_count(), 0);
+    }
+
+    #[test]
+    fn supermove_limit_leaves_out_the_empty_destination() {
+        // (0 + 1) × 2^2, with the destination not counted among the three empty columns
+        let game = supermove_board("7C 8D 9C TD", ": QD JC\n");
+        assert_eq!(game.max_sequence_move_size(15), 4);
+        assert_eq!(game.max_sequence_move_size(9), 8);
+        assert_eq!(game.check_move(8, 15, 5), Err(MoveError::SequenceTooLong{count: 5, max: 4}));
+        assert_eq!(game.check_move(8, 15, 4), Ok(()));
+        assert_eq!(game.sequence_move_size(8, 15), Some(4));
+    }
+
+    #[test]
+    fn each_rule_broken_gives_its_own_reason() {
+        // Deal #1 starts with 6S 9C 2H 6H 6C 3D 8C TC on top of the columns
+        let game = Game::from_deal_number(1);
+        assert_eq!(game.check_move(4, 8, 1), Err(MoveError::SourceEmpty));
+        assert_eq!(game.check_move(8, 8, 1), Err(MoveError::SameStack));
+        assert_eq!(game.check_move(8, 9, 2), Err(MoveError::NotASequence));
+        assert_eq!(game.check_move(8, 4, 2), Err(MoveError::SequenceOutsideTableau));
+        assert_eq!(game.check_move(9, 15, 1), Err(MoveError::SameColor{card: "9C".parse().unwrap(), onto: "TC".parse().unwrap()}));
+        assert_eq!(game.check_move(8, 9, 1), Err(MoveError::WrongRank{card: "6S".parse().unwrap(), onto: Some("9C".parse().unwrap())}));
+        assert_eq!(game.check_move(8, 3, 1), Err(MoveError::WrongRank{card: "6S".parse().unwrap(), onto: None}));
+        assert_eq!(game.check_move(8, 0, 1), Err(MoveError::WrongFoundationSuit{card: "6S".parse().unwrap(), foundation: Suit::Hearts}));
+
+        let game = supermove_board("7C 8D 9C TD", ": QD JC\n");
+        assert_eq!(game.check_move(8, 15, 5), Err(MoveError::SequenceTooLong{count: 5, max: 4}));
+
+        let mut game = Game::from_deal_number(1);
+        game.replay("1a").unwrap();
+        assert_eq!(game.check_move(9, 4, 1), Err(MoveError::FreeCellOccupied));
+        game.replay("2b 3c 4d").unwrap();
+        let start = game.board_string();
+        assert_eq!(game.send_to_foundation_or_free_cell(12), Err(MoveError::NoFreeCell));
+        // 3D has no black 4 to go on and no free cell left
+        game.highlight_stack(13);
+        assert_eq!(game.smart_send(), Err(MoveError::NoDestination));
+        assert_eq!(game.board_string(), start);
+    }
+
+    #[test]
+    fn cards_are_sent_to_their_foundation_before_a_free_cell_but_never_off_a_foundation() {
+        let mut game = nearly_won_board();
+        game.send_to_foundation_or_free_cell(8).unwrap();
+        assert_eq!(game.field[2].len(), 10);
+        game.send_to_foundation_or_free_cell(8).unwrap();
+        assert_eq!(game.field[4].len(), 1);
+
+        let start = game.board_string();
+        assert_eq!(game.send_to_foundation_or_free_cell(2), Err(MoveError::NoDestination));
+        assert_eq!(game.board_string(), start);
+    }
+
+    #[test]
+    fn stack_keys_select_deselect_and_move() {
+        let mut game = Game::from_deal_number(1);
+        // Empty stacks cannot be selected
+        game.handle_stack_press(4);
+        assert_eq!(game.selected_stack(), None);
+
+        game.handle_stack_press(8);
+        assert_eq!(game.selected_stack(), Some(8));
+        game.handle_stack_press(8);
+        assert_eq!(game.selected_stack(), None);
+
+        game.handle_stack_press(8);
+        game.handle_stack_press(5);
+        assert_eq!(game.selected_stack(), None);
+        assert_eq!(game.move_notation().unwrap(), "1b");
+
+        // A move the rules do not allow keeps the selection and explains itself
+        game.handle_stack_press(9);
+        game.handle_stack_press(15);
+        assert_eq!(game.selected_stack(), Some(9));
+        assert!(game.status_message().is_some());
+        assert_eq!(game.move_count(), 1);
+    }
+
+    #[test]
+    fn foundation_key_moves_the_selected_or_else_the_highlighted_card() {
+        let mut game = nearly_won_board();
+        game.highlight_stack(8);
+        game.handle_foundation_press();
+        assert_eq!(game.field[2].len(), 10);
+        assert_eq!(game.highlighted_card, 2);
+
+        game.highlight_stack(8);
+        game.handle_stack_press(11);
+        game.handle_foundation_press();
+        assert_eq!(game.field[0].len(), 10);
+
+        // JC cannot go on the nine of clubs yet
+        game.highlight_stack(8);
+        game.handle_foundation_press();
+        assert_eq!(game.field[1].len(), 9);
+        let error = MoveError::WrongRank{card: "JC".parse().unwrap(), onto: Some("9C".parse().unwrap())};
+        assert_eq!(game.status_message(), Some(error.to_string().as_str()));
+    }
+
+    #[test]
+    fn cursor_skips_empty_stacks_and_wraps_around() {
+        let mut game = Game::from_deal_number(1);
+        assert_eq!(game.highlighted_card, 8);
+        game.move_cursor_left();
+        assert_eq!(game.highlighted_card, 15);
+        game.move_cursor_right();
+        assert_eq!(game.highlighted_card, 8);
+        // Nothing is above the first column yet
+        game.move_cursor_up();
+        assert_eq!(game.highlighted_card, 8);
+    }
+
+    #[test]
+    fn cursor_takes_in_the_sequence_then_moves_up() {
+        let mut game = nearly_won_board();
+        game.highlight_stack(8);
+        for depth in 2..=4 {
+            game.move_cursor_up();
+            assert_eq!((game.highlighted_card, game.highlighted_depth), (8, depth));
+        }
+        game.move_cursor_down();
+        assert_eq!(game.highlighted_depth, 3);
+        // Past the whole sequence of KS QH JC TD, the cursor goes up to the foundation above the first column
+        game.move_cursor_up();
+        game.move_cursor_up();
+        assert_eq!((game.highlighted_card, game.highlighted_depth), (0, 1));
+        game.move_cursor_down();
+        assert_eq!((game.highlighted_card, game.highlighted_depth), (8, 1));
+
+        // The free cell above the fifth column is empty, so the cursor stays put unless cards are selected
+        game.highlight_stack(12);
+        game.move_cursor_up();
+        assert_eq!(game.highlighted_card, 12);
+        game.handle_card_press();
+        game.move_cursor_up();
+        assert_eq!(game.highlighted_card, 4);
+        game.move_cursor_down();
+        assert_eq!(game.highlighted_card, 12);
+    }
+
+    #[test]
+    fn cursor_only_stops_where_the_selection_fits() {
+        let mut game = nearly_won_board();
+        game.highlight_stack(8);
+        game.handle_card_press();
+        // TD fits in the empty columns and on its foundation, but on none of the other columns
+        game.move_cursor_right();
+        assert_eq!(game.highlighted_card, 13);
+        game.move_cursor_right();
+        game.move_cursor_right();
+        game.move_cursor_right();
+        assert_eq!(game.highlighted_card, 2);
+        game.move_cursor_left();
+        assert_eq!(game.highlighted_card, 15);
+    }
+
+    #[test]
+    fn smart_send_prefers_foundations_then_tableau_cards_then_empty_columns_then_free_cells() {
+        let game = Game::from_board_string("\
+            Foundations: H-7 C-9 D-9 S-9\n\
+            : KS QH JC TD\n\
+            : KH QS JD TC\n\
+            : KD QC JH TS\n\
+            : KC QD JS TH\n\
+            : 8H 9H\n").unwrap();
+        assert_eq!(game.smart_send_move(8, 1), Some(Move{from: 8, to: 2, count: 1}));
+        // 9H fits on both black tens and goes on the leftmost one
+        assert_eq!(game.smart_send_move(12, 1), Some(Move{from: 12, to: 9, count: 1}));
+        // TH fits on no card, so the whole sequence above KC goes to the leftmost empty column
+        assert_eq!(game.smart_send_move(11, 1), Some(Move{from: 11, to: 13, count: 3}));
+        assert_eq!(game.smart_send_move(11, 2), Some(Move{from: 11, to: 13, count: 2}));
+
+        // Nothing on deal #1 fits anywhere but the free cells, which fill up from the left
+        let mut game = Game::from_deal_number(1);
+        assert_eq!(game.smart_send_move(8, 1), Some(Move{from: 8, to: 4, count: 1}));
+        game.replay("1a").unwrap();
+        assert_eq!(game.smart_send_move(9, 1), Some(Move{from: 9, to: 5, count: 1}));
+        // A card in a free cell is not sent on to another free cell
+        assert_eq!(game.smart_send_move(4, 1), None);
+    }
+
+    #[test]
+    fn clock_runs_from_the_first_move_until_paused_or_won() {
+        let mut game = nearly_won_board();
+        game.resume_clock();
+        assert!(!game.is_clock_running());
+        assert_eq!(game.elapsed_time(), Duration::ZERO);
+
+        game.replay("1h").unwrap();
+        assert!(game.is_clock_running());
+        game.pause_clock();
+        assert!(!game.is_clock_running());
+        let paused_time = game.elapsed_time();
+        assert_eq!(game.elapsed_time(), paused_time);
+        game.resume_clock();
+        assert!(game.is_clock_running());
+
+        game.quick_stack_to_foundations();
+        assert!(game.is_won() && !game.is_clock_running());
+        let won_time = game.elapsed_time();
+        game.resume_clock();
+        assert!(!game.is_clock_running());
+        assert_eq!(game.elapsed_time(), won_time);
+    }
+
+    #[test]
+    fn idle_game_has_nothing_to_tick() {
+        let mut game = Game::from_deal_number(1);
+        assert_eq!(game.next_tick(), None);
+        game.replay("1a").unwrap();
+        let next_tick = game.next_tick().unwrap();
+        assert!(next_tick <= Instant::now() + Duration::from_secs(1));
+        game.pause_clock();
+        assert_eq!(game.next_tick(), None);
+    }
+
+    #[test]
+    fn status_message_stops_flashing_then_goes_away() {
+        let mut game = Game::from_deal_number(1);
+        let shown = Instant::now();
+        game.status_message_opt = Some(("New message".to_owned(), shown));
+        assert_eq!(game.next_tick(), Some(shown + STATUS_MESSAGE_FLASH_TIME));
+
+        let shown = Instant::now().checked_sub(Duration::from_secs(1)).unwrap();
+        game.status_message_opt = Some(("Older message".to_owned(), shown));
+        assert_eq!(game.next_tick(), Some(shown + STATUS_MESSAGE_TIME));
+        game.tick();
+        assert_eq!(game.status_message(), Some("Older message"));
+
+        let shown = Instant::now().checked_sub(STATUS_MESSAGE_TIME).unwrap();
+        game.status_message_opt = Some(("Old message".to_owned(), shown));
+        game.tick();
+        assert_eq!(game.status_message(), None);
+        assert_eq!(game.next_tick(), None);
+    }
+
+    #[test]
+    fn hint_selects_a_legal_move_and_is_counted() {
+        let mut game = Game::from_deal_number(1);
+        game.show_hint();
+        let hint = Move{from: game.selected_stack().unwrap(), to: game.highlighted_card, count: 1};
+        assert!(game.legal_moves().iter().any(|legal_move| (legal_move.from, legal_move.to) == (hint.from, hint.to)));
+        assert_eq!(game.hints_used(), 1);
+
+        // Pressing on the highlighted stack follows the hint
+        game.handle_card_press();
+        assert_eq!(game.undo_history.back().map(|action| (action[0].from, action[0].to)), Some((hint.from, hint.to)));
+        game.show_hint();
+        assert_eq!(game.hints_used(), 2);
+    }
+
+    #[test]
+    fn heuristic_hint_prefers_the_foundations() {
+        let game = nearly_won_board();
+        assert_eq!(game.heuristic_hint(), Some(Move{from: 8, to: 2, count: 1}));
+    }
+
+    #[test]
+    fn switching_auto_play_on_stacks_the_safe_cards() {
+        let start = nearly_won_board();
+        let mut game = start.clone();
+        game.toggle_auto_play();
+        assert!(game.is_won());
+
+        // The stacked cards come back in one undo, and turning auto-play off leaves the position alone
+        game.perform_undo();
+        assert_eq!(game.board_string(), start.board_string());
+        game.toggle_auto_play();
+        assert_eq!(game.board_string(), start.board_string());
+    }
+
+    #[test]
+    fn new_games_keep_the_modes_of_the_game_they_replace() {
+        let mut game = Game::from_deal_number(1);
+        game.toggle_auto_play();
+        game.toggle_high_contrast();
+        let mut next_game = Game::from_deal_number(2);
+        next_game.copy_modes_from(&game);
+        assert!(next_game.auto_play && next_game.high_contrast);
+    }
+}
@@ -0,0 +1,219 @@
+//! Reading and writing positions in the board layout of Freecell Solver (fc-solve)
+//!
+//! A board is a `Foundations:` line with the highest card of each suit on the foundations, such as `H-5` or `S-0`,
+//! a `Freecells:` line with the card in each free cell or `-` for an empty one, and one line per tableau column
+//! listing its cards from the bottom of the column to the top, optionally after a `:`. For example:
+//!
+//! ```text
+//! Foundations: H-0 C-2 D-A S-0
+//! Freecells: KD - - -
+//! : 4C 2C 9C 8C QS 4S 2H
+//! : 5H QH 3C AC 3H 4H QD
+//! ```
+
+use std::{fmt, fs, io, path::Path};
+
+use crate::cards::{Card, Rank, Suit};
+
+use super::{Game, PositionError, FIELD_SIZE, FOUNDATIONS, FREE_CELLS, TABLEAU_SIZE};
+
+/// The label of the line holding the foundations.
+const FOUNDATIONS_LABEL: &str = "Foundations:";
+/// The label of the line holding the free cells.
+const FREE_CELLS_LABEL: &str = "Freecells:";
+/// How an empty free cell is written.
+const EMPTY_FREE_CELL: &str = "-";
+
+/// An error that occurred while reading a board.
+#[derive(Debug)]
+pub enum BoardError {
+    /// The board file could not be read.
+    Io(io::Error),
+    /// A line of the board could not be understood.
+    Malformed(String),
+    /// The board does not hold a valid position, such as when it is missing some of the 52 cards.
+    InvalidPosition(PositionError),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::Io(error) => write!(f, "{error}"),
+            BoardError::Malformed(problem) => write!(f, "malformed board: {problem}"),
+            BoardError::InvalidPosition(error) => write!(f, "invalid position: {error}")
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl From<io::Error> for BoardError {
+    fn from(error: io::Error) -> Self {
+        BoardError::Io(error)
+    }
+}
+
+impl Game {
+    /// Writes the current position in the fc-solve board layout.
+    #[must_use]
+    pub fn board_string(&self) -> String {
+        let foundations: Vec<String> = Suit::ALL.iter().zip(self.foundations()).map(|(suit, foundation)| {
+            let rank_char = foundation.last().map_or('0', |card| card.rank.letter());
+            format!("{}-{rank_char}", suit.letter())
+        }).collect();
+        let free_cells: Vec<String> = self.free_cells().iter()
+            .map(|free_cell| free_cell.last().map_or(EMPTY_FREE_CELL.to_owned(), ToString::to_string))
+            .collect();
+
+        let mut lines = vec![
+            format!("{FOUNDATIONS_LABEL} {}", foundations.join(" ")),
+            format!("{FREE_CELLS_LABEL} {}", free_cells.join(" "))
+        ];
+        for column in self.tableau() {
+            let cards: Vec<String> = column.iter().map(ToString::to_string).collect();
+            lines.push(format!(": {}", cards.join(" ")).trim_end().to_owned());
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Creates a game from a position in the fc-solve board layout, with an empty undo history.
+    /// The foundations and free cells lines may be left out when they are empty, and so may empty columns at the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the board is malformed or does not hold a valid position.
+    pub fn from_board_string(board: &str) -> Result<Game, BoardError> {
+        let mut field: [Vec<Card>; FIELD_SIZE] = core::array::from_fn(|_| Vec::new());
+        let mut columns_read = 0;
+        for line in board.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let malformed = || BoardError::Malformed(format!("cannot read line \"{line}\""));
+            let parse_card = |name: &str| name.parse::<Card>().map_err(|error| BoardError::Malformed(error.to_string()));
+
+            if let Some(foundations) = line.strip_prefix(FOUNDATIONS_LABEL) {
+                for foundation in foundations.split_whitespace() {
+                    let (suit_name, rank_name) = foundation.split_once('-').ok_or_else(malformed)?;
+                    let suit = parse_card(&format!("A{suit_name}"))?.suit;
+                    let top_rank = if rank_name == "0" {None} else {Some(parse_card(&format!("{rank_name}{suit_name}"))?.rank)};
+                    field[suit.index()] = Rank::ALL.into_iter()
+                        .take_while(|&rank| top_rank.is_some_and(|top_rank| rank <= top_rank))
+                        .map(|rank| Card{rank, suit})
+                        .collect();
+                }
+            } else if let Some(free_cells) = line.strip_prefix(FREE_CELLS_LABEL) {
+                let free_cells: Vec<&str> = free_cells.split_whitespace().collect();
+                if free_cells.len() > FREE_CELLS {
+                    return Err(BoardError::Malformed(format!("expected at most {FREE_CELLS} free cells, found {}", free_cells.len())));
+                }
+                for (i, name) in free_cells.into_iter().enumerate() {
+                    field[FOUNDATIONS + i] = if name == EMPTY_FREE_CELL {Vec::new()} else {vec![parse_card(name)?]};
+                }
+            } else {
+                if columns_read == TABLEAU_SIZE {
+                    return Err(BoardError::Malformed(format!("expected at most {TABLEAU_SIZE} columns")));
+                }
+                let cards = line.strip_prefix(':').unwrap_or(line);
+                field[FOUNDATIONS + FREE_CELLS + columns_read] = cards.split_whitespace().map(parse_card).collect::<Result<_, _>>()?;
+                columns_read += 1;
+            }
+        }
+        Game::from_field(field).map_err(BoardError::InvalidPosition)
+    }
+
+    /// Loads a game from a file holding a position in the fc-solve board layout, see `from_board_string`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BoardError` if the file cannot be read, is malformed, or does not hold a valid position.
+    pub fn load_board_file(path: &Path) -> Result<Game, BoardError> {
+        Game::from_board_string(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::BoardError;
+    use crate::{cards::{Card, Rank, Suit}, game::{Game, PositionError}};
+
+    /// A position with cards on the foundations and in the free cells and an empty column between full ones.
+    const BOARD: &str = "\
+        Foundations: H-9 C-9 D-9 S-8\n\
+        Freecells: - 9S - TD\n\
+        : KS QH JC\n\
+        : KH QS\n\
+        : KD QC JH\n\
+        :\n\
+        : KC QD JS TH\n\
+        : TS\n\
+        : TC JD\n\
+        :\n";
+
+    #[test]
+    fn board_string_reads_back_the_same() {
+        let game = Game::from_board_string(BOARD).unwrap();
+        assert_eq!(game.board_string(), BOARD);
+        assert_eq!(game.free_cells()[1], [Card{rank: Rank::Nine, suit: Suit::Spades}]);
+        assert!(game.tableau()[3].is_empty());
+
+        let deal = Game::from_deal_number(164);
+        assert_eq!(Game::from_board_string(&deal.board_string()).unwrap().field(), deal.field());
+    }
+
+    #[test]
+    fn empty_lines_and_columns_may_be_left_out() {
+        let game = Game::from_board_string("\
+            # Deal #1 with its first column in the free cells and no foundations line\n\
+            Freecells: 6S 6D 3S 4C\n\
+            \n\
+            : JD KD 2S\n\
+            2D KC KS 5C TD 8S 9C\n\
+            : 9H 9S 9D TS 4S 8D 2H\n\
+            : JC 5S QD QH TH QS 6H\n\
+            : 5D AD JS 4H 8H 6C\n\
+            : 7H QC AS AC 2C 3D\n\
+            : 7C KH AH 4D JH 8C\n\
+            : 5H 3H 3C 7S 7D TC\n").unwrap();
+        assert!(game.foundations().iter().all(Vec::is_empty));
+        assert_eq!(game.tableau()[1].len(), 7);
+
+        let game = Game::from_board_string("Foundations: H-K C-K D-K S-K\n").unwrap();
+        assert!(game.is_won());
+        assert!(game.board_string().starts_with("Foundations: H-K C-K D-K S-K\nFreecells: - - - -\n:\n"));
+    }
+
+    #[test]
+    fn malformed_boards_are_rejected() {
+        for board in [
+            "Foundations: H9 C-9 D-9 S-9\n",
+            "Foundations: X-9 C-9 D-9 S-9\n",
+            "Foundations: H-X C-9 D-9 S-9\n",
+            "Freecells: - - - - -\n",
+            "Freecells: 1S - - -\n",
+            ": KS QX\n",
+            &format!("{BOARD}: AS\n"),
+        ] {
+            assert!(matches!(Game::from_board_string(board), Err(BoardError::Malformed(_))), "{board}");
+        }
+    }
+
+    #[test]
+    fn invalid_positions_are_rejected() {
+        let missing_card = BOARD.replace(": TS\n", ":\n");
+        let duplicate_card = BOARD.replace(": TS\n", ": JD\n");
+        for (board, expected) in [
+            (missing_card, PositionError::CardCount(51)),
+            (duplicate_card, PositionError::DuplicateCard(Card{rank: Rank::Jack, suit: Suit::Diamonds})),
+        ] {
+            match Game::from_board_string(&board) {
+                Err(BoardError::InvalidPosition(error)) => assert_eq!(error, expected),
+                result => panic!("expected {expected:?}, got {:?}", result.map(|game| game.board_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn missing_board_file_is_an_io_error() {
+        assert!(matches!(Game::load_board_file(Path::new("/nonexistent/board.txt")), Err(BoardError::Io(_))));
+    }
+}
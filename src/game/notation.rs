@@ -0,0 +1,283 @@
+//! Reading and writing moves in standard `FreeCell` move notation
+//!
+//! Each move is written as its source followed by its destination, where the tableau columns are `1` to `8`
+//! from left to right, the free cells are `a` to `d` from left to right and the foundations are `h`.
+//! For example, "3a" moves the top card of the third column into the first free cell, "4h" moves the top card
+//! of the fourth column to its foundation and "18" moves cards from the first column onto the eighth.
+//! The number of cards moved is usually not written: a move between columns carries the longest sequence that fits,
+//! just like it does when the player makes it. A move that carries fewer cards, such as part of a sequence moved
+//! into an empty column, is followed by its number of cards after a slash, as in "15/2". The slash is an extension
+//! of this game that other `FreeCell` programs do not read, so it is only written where a move needs it.
+//! Standard notation has no way to write a move that takes a card back off a foundation.
+
+use std::fmt;
+
+use super::{Game, Move, MoveError, FIELD_SIZE, FOUNDATIONS, FREE_CELLS};
+
+/// The character that stands for the foundations in move notation.
+const FOUNDATION_CHAR: char = 'h';
+/// The characters that stand for the free cells in move notation, from left to right.
+const FREE_CELL_CHARS: [char; FREE_CELLS] = ['a', 'b', 'c', 'd'];
+
+/// An error that occurred while reading or writing moves in standard notation.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NotationError {
+    /// A move is not written in standard notation. Holds the move as written.
+    Malformed(String),
+    /// A move cannot be played in the position it was read in. Holds the move as written and the reason.
+    IllegalMove(String, MoveError),
+    /// A move takes a card back off a foundation, which cannot be written in standard notation.
+    FromFoundation,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotationError::Malformed(notation) => write!(f, "\"{notation}\" is not a move in standard notation"),
+            NotationError::IllegalMove(notation, reason) => write!(f, "move \"{notation}\" cannot be played here, {reason}"),
+            NotationError::FromFoundation => write!(f, "moves off a foundation have no notation")
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Writes the move in standard notation, such as "3a", without its number of cards.
+/// `Game::moves_notation` adds the number where the move needs it to replay the same way.
+/// Cards moved off a foundation are written with an `h` source, which standard notation cannot read back,
+/// so `Game::moves_notation` refuses to write them.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", location_char(self.from), location_char(self.to))
+    }
+}
+
+/// Returns the character standing for a stack of the field in move notation.
+fn location_char(index: usize) -> char {
+    if index < FOUNDATIONS {
+        FOUNDATION_CHAR
+    } else if index < FOUNDATIONS + FREE_CELLS {
+        FREE_CELL_CHARS[index - FOUNDATIONS]
+    } else {
+        #[allow(clippy::cast_possible_truncation)]
+        char::from_digit((index - FOUNDATIONS - FREE_CELLS + 1) as u32, 10).unwrap_or('?')
+    }
+}
+
+/// Reads the field index of a free cell or tableau column from its character in move notation.
+fn location_index(location: char) -> Option<usize> {
+    let location = location.to_ascii_lowercase();
+    if let Some(free_cell) = FREE_CELL_CHARS.iter().position(|&c| c == location) {
+        return Some(FOUNDATIONS + free_cell);
+    }
+    let column = location.to_digit(10)? as usize;
+    let index = FOUNDATIONS + FREE_CELLS + column.checked_sub(1)?;
+    (index < FIELD_SIZE).then_some(index)
+}
+
+impl Game {
+    /// Reads a move written in standard notation and works out the cards it moves in the current position,
+    /// unless their number is written after a slash.
+    /// A move to the foundations goes to the foundation of the moved card's suit.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotationError` if the move is not written in standard notation or cannot be played in the current position.
+    pub fn parse_move(&self, notation: &str) -> Result<Move, NotationError> {
+        let malformed = || NotationError::Malformed(notation.to_owned());
+        let (locations, count_opt) = match notation.split_once('/') {
+            Some((locations, count)) => (locations, Some(count.parse::<usize>().ok().filter(|&count| count > 0).ok_or_else(malformed)?)),
+            None => (notation, None)
+        };
+        let mut chars = locations.chars();
+        let (Some(from_char), Some(to_char), None) = (chars.next(), chars.next(), chars.next()) else {return Err(malformed())};
+
+        // The foundations can only be told apart by the card moved onto them, so they cannot be a source
+        let from = location_index(from_char).ok_or_else(malformed)?;
+        let illegal = |reason| NotationError::IllegalMove(notation.to_owned(), reason);
+        let to = if to_char.eq_ignore_ascii_case(&FOUNDATION_CHAR) {
+            let card = self.field[from].last().ok_or(illegal(MoveError::SourceEmpty))?;
+            card.suit.index()
+        } else {
+            location_index(to_char).ok_or_else(malformed)?
+        };
+
+        let count = match count_opt {
+            Some(count) => self.check_move(from, to, count).map(|()| count).map_err(illegal)?,
+            None => self.player_move_size(from, to).map_err(illegal)?
+        };
+        Ok(Move{from, to, count})
+    }
+
+    /// Plays a list of moves written in standard notation, separated by whitespace, recording each one in the undo history.
+    /// Anything from a `#` to the end of its line is a comment and is skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NotationError` for the first move that is malformed or cannot be played.
+    /// The moves before it stay played.
+    pub fn replay(&mut self, notation: &str) -> Result<(), NotationError> {
+        let moves = notation.lines().flat_map(|line| line.split('#').next().unwrap_or_default().split_whitespace());
+        for move_notation in moves {
+            let parsed_move = self.parse_move(move_notation)?;
+            self.apply_move(parsed_move).map_err(|reason| NotationError::IllegalMove(move_notation.to_owned(), reason))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the moves that led from the deal to the current position in standard notation, separated by spaces,
+    /// so that `replay` plays them the same way.
+    /// Moves that were undone are left out, and so are the oldest moves once the undo history is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotationError::FromFoundation` if one of the moves takes a card back off a foundation.
+    pub fn move_notation(&self) -> Result<String, NotationError> {
+        // Take back every recorded move to find the position the first of them was played in
+        let mut start = self.clone();
+        for undone_move in self.undo_history.iter().flatten().rev() {
+            start.execute_move(undone_move.to, undone_move.from, undone_move.count);
+        }
+        let moves: Vec<Move> = self.undo_history.iter().flatten().copied().collect();
+        start.moves_notation(&moves)
+    }
+
+    /// Writes moves played one after the other from the current position in standard notation, separated by spaces,
+    /// such as the moves of a solver's solution.
+    /// A move that carries fewer cards than `parse_move` would work out is written with its number of cards,
+    /// so that `replay` plays every move the same way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotationError::FromFoundation` if one of the moves takes a card back off a foundation.
+    pub fn moves_notation(&self, moves: &[Move]) -> Result<String, NotationError> {
+        let mut position = self.clone();
+        let notations = moves.iter().map(|&game_move| {
+            if game_move.from < FOUNDATIONS {
+                return Err(NotationError::FromFoundation);
+            }
+            let notation = if position.player_move_size(game_move.from, game_move.to) == Ok(game_move.count) {
+                game_move.to_string()
+            } else {
+                format!("{game_move}/{}", game_move.count)
+            };
+            position.execute_move(game_move.from, game_move.to, game_move.count);
+            Ok(notation)
+        }).collect::<Result<Vec<String>, NotationError>>()?;
+        Ok(notations.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotationError;
+    use crate::game::{solver::{self, Solution, SolverLimits}, Game, Move, MoveError};
+
+    /// Foundations up to the nines, with a four card sequence in the first column and the last two columns empty.
+    const BOARD: &str = "\
+        Foundations: H-9 C-9 D-9 S-9\n\
+        : KS QH JC TD\n\
+        : KH QS\n\
+        : KD QC JH\n\
+        : KC QD JS TH\n\
+        : TS\n\
+        : TC JD\n\
+        :\n\
+        :\n";
+
+    /// Checks that the moves written by `move_notation` replay from the starting position to the same position.
+    fn assert_replays_the_same_way(start: &Game, game: &Game) {
+        let mut replayed = start.clone();
+        replayed.replay(&game.move_notation().unwrap()).unwrap();
+        assert_eq!(replayed.field(), game.field());
+    }
+
+    #[test]
+    fn bare_move_carries_longest_sequence() {
+        let game = Game::from_board_string(BOARD).unwrap();
+        assert_eq!(game.parse_move("17"), Ok(Move{from: 8, to: 14, count: 4}));
+        assert_eq!(game.parse_move("5a"), Ok(Move{from: 12, to: 4, count: 1}));
+        assert_eq!(game.parse_move("5h"), Ok(Move{from: 12, to: 3, count: 1}));
+    }
+
+    #[test]
+    fn move_with_count_carries_that_many_cards() {
+        let game = Game::from_board_string(BOARD).unwrap();
+        assert_eq!(game.parse_move("17/2"), Ok(Move{from: 8, to: 14, count: 2}));
+        assert_eq!(game.parse_move("17/5"), Err(NotationError::IllegalMove("17/5".to_owned(), MoveError::NotASequence)));
+    }
+
+    #[test]
+    fn malformed_moves_are_rejected() {
+        let game = Game::from_board_string(BOARD).unwrap();
+        for notation in ["1", "1a2", "19", "h1", "17/", "17/0", "17/x", "x7"] {
+            assert_eq!(game.parse_move(notation), Err(NotationError::Malformed(notation.to_owned())), "{notation}");
+        }
+        assert_eq!(game.parse_move("7a"), Err(NotationError::IllegalMove("7a".to_owned(), MoveError::SourceEmpty)));
+    }
+
+    #[test]
+    fn partial_sequence_into_empty_column_replays_the_same_way() {
+        let start = Game::from_board_string(BOARD).unwrap();
+        let mut game = start.clone();
+        game.apply_move(Move{from: 8, to: 14, count: 2}).unwrap();
+        game.apply_move(Move{from: 9, to: 15, count: 1}).unwrap();
+        game.apply_move(Move{from: 14, to: 8, count: 2}).unwrap();
+        assert_eq!(game.move_notation().unwrap(), "17/2 28/1 71");
+        assert_replays_the_same_way(&start, &game);
+    }
+
+    #[test]
+    fn cards_picked_up_into_empty_column_replay_the_same_way() {
+        let start = Game::from_board_string(BOARD).unwrap();
+        let mut game = start.clone();
+        // Take in QH JC TD of the first column and drop them on the seventh
+        game.highlight_stack(8);
+        game.move_cursor_up();
+        game.move_cursor_up();
+        game.handle_card_press();
+        game.handle_stack_press(14);
+        assert_eq!(game.field()[14].len(), 3);
+        assert_eq!(game.move_notation().unwrap(), "17/3");
+        assert_replays_the_same_way(&start, &game);
+    }
+
+    #[test]
+    fn card_sent_into_empty_column_replays_the_same_way() {
+        let start = Game::from_board_string(BOARD).unwrap();
+        let mut game = start.clone();
+        // QS has nowhere to go but an empty column, and sending it leaves KH behind
+        game.highlight_stack(9);
+        game.smart_send().unwrap();
+        assert_eq!(game.field()[9].len(), 1);
+        assert_eq!(game.move_notation().unwrap(), "27/1");
+        assert_replays_the_same_way(&start, &game);
+    }
+
+    #[test]
+    fn deal_replays_the_same_way() {
+        let start = Game::from_deal_number(1);
+        let mut game = start.clone();
+        game.replay("1a 2b 3c 4d").unwrap();
+        assert_eq!(game.move_notation().unwrap(), "1a 2b 3c 4d");
+        assert_replays_the_same_way(&start, &game);
+    }
+
+    #[test]
+    fn solver_solution_replays_to_a_win() {
+        // The solution of deal #164 moves a single card into an empty column while a longer sequence would fit
+        let game = Game::from_deal_number(164);
+        let Solution::Solvable(moves) = solver::solve(&game, &SolverLimits::default()) else {panic!("deal #164 is solvable")};
+        let mut replayed = game.clone();
+        replayed.replay(&game.moves_notation(&moves).unwrap()).unwrap();
+        assert!(replayed.is_won());
+    }
+
+    #[test]
+    fn moves_off_a_foundation_are_not_written() {
+        let mut game = Game::from_board_string(BOARD).unwrap();
+        game.replay("5h").unwrap();
+        game.apply_move(Move{from: 3, to: 14, count: 1}).unwrap();
+        assert_eq!(game.move_notation(), Err(NotationError::FromFoundation));
+    }
+}
@@ -1,12 +1,21 @@
-//! Utilities for printing the state of the `FreeCell` game to the terminal
+//! Utilities for printing the state of the `FreeCell` game to the terminal, or to any other output stream
 
-use std::io::{self, Write};
+use std::{io::{self, Write}, time::Duration};
 
-use crossterm::{cursor, style::{self, Stylize}, terminal, QueueableCommand};
+use crossterm::style;
 
-use crate::{cards::Card, game::Game, MIN_TERMINAL_WIDTH};
+use crate::{cards::{Card, Color, Suit}, game::Game, keys::{Command, KeyBindings}};
 
-use super::{CLUBS, DIAMONDS, FREE_CELLS, HEARTS, RANKS, SPADES, SUITS, FOUNDATIONS, TABLEAU_SIZE};
+use self::screen::{CellStyle, Screen};
+
+use super::{stats::Stats, FIELD_SIZE, STATUS_MESSAGE_FLASH_TIME, FREE_CELLS, FOUNDATIONS, TABLEAU_SIZE};
+
+pub mod screen;
+
+/// Minimum width of the terminal window supported by the game.
+pub const MIN_TERMINAL_WIDTH: u16 = 60;
+/// Minimum height of the terminal window supported by the game.
+pub const MIN_TERMINAL_HEIGHT: u16 = 24;
 
 /// Typical height of the game board in number of lines. This is used for vertically centering the win screen.
 const TYPICAL_BOARD_HEIGHT: u16 = 24;
@@ -18,150 +27,362 @@ const CARD_PRINT_HEIGHT: u16 = 5;
 /// Vertical printing offset (measured in characters) between cards that are stacked on top of each other on the tableau.
 const TABLEAU_VERTICAL_OFFSET: u16 = 2;
 
-/// Default width of the terminal window.
-const DEFAULT_TERMINAL_WIDTH: u16 = 80;
-/// Default height of the terminal window.
-const DEFAULT_TERMINAL_HEIGHT: u16 = 24;
+/// The actions named in the bottom bar, in the order they are shown, with their labels.
+const BOTTOM_BAR_COMMANDS: [(Command, &str); 5] = [
+    (Command::NewGame, "New"), (Command::Undo, "Undo"), (Command::Hint, "Hint"), (Command::Help, "Help"), (Command::Quit, "Quit")
+];
+/// The order in which the actions of the bottom bar get its space, as indices into `BOTTOM_BAR_COMMANDS`.
+/// Help comes first, since the help screen lists all the others, and Undo next, since it is needed most during a game.
+const BOTTOM_BAR_PRIORITY: [usize; 5] = [3, 1, 0, 4, 2];
 
-/// Strings representing suits in the order: empty, hearts, clubs, diamonds, spades.
-const SUIT_STRINGS: [&str; SUITS as usize + 1] = [" ", "♥", "♣", "♦", "♠"];
-/// Strings representing ranks in the order: empty, A, 2, 3, ..., 10, J, Q, K.
-const RANK_STRINGS: [&str; RANKS as usize + 1] = [" ", "A", "2", "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K"];
+/// Width of the help and statistics screens in characters.
+const OVERLAY_WIDTH: usize = MIN_TERMINAL_WIDTH as usize - 4;
 
 impl Game {
-    /// Prints the game state to the terminal.
+    /// Prints the game state on the screen.
     ///
     /// # Arguments
     ///
-    /// * `out` - A mutable reference to the standard output stream.
+    /// * `screen` - The screen to draw the frame on.
+    /// * `bindings` - The active key bindings, which the bottom bar and messages refer to.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// A Result indicating success or an error if writing to the output stream fails.
-    pub fn print(&self, out: &mut io::Stdout) -> Result<(), io::Error> {
+    /// Returns an `io::Error` if writing to the screen's output stream fails.
+    pub fn print(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) -> Result<(), io::Error> {
+        screen.begin_frame();
         if self.is_won() {
-            out.queue(style::SetAttribute(style::Attribute::Dim))?;
-            self.print_board(out)?;
-            out.queue(style::SetAttribute(style::Attribute::Reset))?;
-            Game::print_chrome(out, self.move_count)?;
-            Game::print_win(out)?;
+            self.print_dimmed(screen, bindings);
+            Game::print_win(screen, self.hints_used, bindings);
+        } else if self.is_stalemate() {
+            self.print_dimmed(screen, bindings);
+            Game::print_stalemate(screen, bindings);
         } else {
-            self.print_board(out)?;
-            Game::print_chrome(out, self.move_count)?;
+            self.print_board(screen);
+            self.print_chrome(screen, bindings);
+        }
+        screen.end_frame()
+    }
+
+    /// Prints the game state dimmed, with a prompt asking whether to resume it.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen` - The screen to draw the frame on.
+    /// * `bindings` - The active key bindings, which the bottom bar refers to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if writing to the screen's output stream fails.
+    pub fn print_resume_prompt(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) -> Result<(), io::Error> {
+        screen.begin_frame();
+        self.print_dimmed(screen, bindings);
+        let prompt_width = 20;
+        let prompt_height = 4;
+        Game::print_string_at_coord(screen,
+        "╭──────────────────╮\n\
+                 │ Resume game?     │\n\
+                 │ Yes: y    No: n  │\n\
+                 ╰──────────────────╯",
+                MIN_TERMINAL_WIDTH / 2 - prompt_width / 2,
+                TYPICAL_BOARD_HEIGHT / 2 - prompt_height / 2);
+        screen.end_frame()
+    }
+
+    /// Prints the game state dimmed, with the help screen listing the active key bindings over it.
+    /// Actions that share a description, such as the ones for each tableau column, share a line listing their main keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen` - The screen to draw the frame on.
+    /// * `bindings` - The active key bindings to list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if writing to the screen's output stream fails.
+    pub fn print_help(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) -> Result<(), io::Error> {
+        screen.begin_frame();
+        self.print_dimmed(screen, bindings);
+
+        let inner_width = OVERLAY_WIDTH - 4;
+        let description_width = 27;
+        let mut lines = vec![format!("╭─ Keys {}╮", "─".repeat(inner_width - 5))];
+        for group in Command::ALL.chunk_by(|a, b| a.description() == b.description()) {
+            let keys: Vec<String> = if let [command] = group {
+                bindings.keys_for(*command).iter().map(ToString::to_string).collect()
+            } else {
+                group.iter().map(|&command| key_name(bindings, command)).collect()
+            };
+            let separator = if group.len() == 1 {", "} else {" "};
+            let keys: String = keys.join(separator).chars().take(inner_width - description_width).collect();
+            lines.push(format!("│ {:<description_width$}{keys:<width$} │", group[0].description(), width = inner_width - description_width));
         }
-        out.flush()?;
-        Ok(())
+        lines.push(overlay_bottom_border(inner_width));
+        Game::print_overlay_box(screen, &lines);
+        screen.end_frame()
     }
 
-    /// Prints the game board layout to the terminal.
-    fn print_board(&self, out: &mut io::Stdout) -> Result<(), io::Error> {
-        out.queue(terminal::Clear(terminal::ClearType::All))?;
+    /// Prints the game state dimmed, with the statistics of all games played over it,
+    /// along with the results on the current deal if it has one.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen` - The screen to draw the frame on.
+    /// * `bindings` - The active key bindings, which the bottom bar refers to.
+    /// * `stats` - The statistics to show.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if writing to the screen's output stream fails.
+    pub fn print_stats(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings, stats: &Stats) -> Result<(), io::Error> {
+        screen.begin_frame();
+        self.print_dimmed(screen, bindings);
+
+        let inner_width = OVERLAY_WIDTH - 4;
+        let label_width = 18;
+        let won_percentage = (stats.won * 100).checked_div(stats.played()).unwrap_or(0);
+        let average_moves = stats.average_moves().map_or("-".to_owned(), |average_moves| average_moves.to_string());
+        let mut rows = vec![
+            ("Games played".to_owned(), stats.played().to_string()),
+            ("Games won".to_owned(), format!("{} ({won_percentage}%)", stats.won)),
+            ("Games abandoned".to_owned(), stats.abandoned.to_string()),
+            ("Current streak".to_owned(), stats.current_streak.to_string()),
+            ("Best streak".to_owned(), stats.best_streak.to_string()),
+            ("Average moves".to_owned(), average_moves),
+            ("Average time".to_owned(), stats.average_time().map_or("-".to_owned(), format_time)),
+            ("Best time".to_owned(), stats.best_time.map_or("-".to_owned(), format_time)),
+            ("Hints used".to_owned(), stats.hints_used.to_string())
+        ];
+        if let Some(deal) = self.deal {
+            let deal_stats = stats.deals.get(&deal).copied().unwrap_or_default();
+            let fewest_moves = deal_stats.fewest_moves.map_or("-".to_owned(), |fewest_moves| fewest_moves.to_string());
+            rows.push((deal.to_string(), format!("won {} of {}, fewest moves {fewest_moves}", deal_stats.won, deal_stats.played())));
+            rows.push(("  Best time".to_owned(), deal_stats.best_time.map_or("-".to_owned(), format_time)));
+            rows.push(("  Hints used".to_owned(), deal_stats.hints_used.to_string()));
+        }
+
+        let mut lines = vec![format!("╭─ Statistics {}╮", "─".repeat(inner_width - 11))];
+        for (label, value) in rows {
+            let value: String = value.chars().take(inner_width - label_width).collect();
+            lines.push(format!("│ {label:<label_width$}{value:<width$} │", width = inner_width - label_width));
+        }
+        lines.push(overlay_bottom_border(inner_width));
+        Game::print_overlay_box(screen, &lines);
+        screen.end_frame()
+    }
+
+    /// Prints the chrome with a pause screen in place of the board, so that the cards cannot be studied while the clock is stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen` - The screen to draw the frame on.
+    /// * `bindings` - The active key bindings, which the bottom bar refers to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if writing to the screen's output stream fails.
+    pub fn print_paused(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) -> Result<(), io::Error> {
+        screen.begin_frame();
+        self.print_chrome(screen, bindings);
+        let prompt_width = 26;
+        let prompt_height = 4;
+        Game::print_string_at_coord(screen,
+        "╭────────────────────────╮\n\
+                 │ Paused                 │\n\
+                 │ Press any key to play  │\n\
+                 ╰────────────────────────╯",
+                MIN_TERMINAL_WIDTH / 2 - prompt_width / 2,
+                TYPICAL_BOARD_HEIGHT / 2 - prompt_height / 2);
+        screen.end_frame()
+    }
+
+    /// Draws the game board dimmed, with the chrome, as a background for a prompt or screen drawn over it.
+    fn print_dimmed(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) {
+        self.print_board(screen);
+        screen.dim_all();
+        self.print_chrome(screen, bindings);
+    }
+
+    /// Draws the game board layout on the screen.
+    fn print_board(&self, screen: &mut Screen<impl Write>) {
 
         for (i, stack) in self.field.iter().enumerate() {
-            let mut top_card = stack.last().copied().unwrap_or_default();
+            let top_card_opt = stack.last().copied();
             let top_card_is_highlighted = self.highlighted_card == i && !self.is_won();
+            let (x, y) = Game::stack_origin(i);
             if i < FOUNDATIONS {
                 // Print foundation
-                // If card is a placeholder, assign a suit for decoration
-                #[allow(clippy::cast_possible_truncation)]
-                if top_card == Card::default() {
-                    top_card = Card{rank: 0, suit: i as u8 + 1};
-                }
-                #[allow(clippy::cast_possible_truncation)]
+                // If it is empty, its placeholder is decorated with its suit
                 Game::print_card_at_coord(
-                    out,
-                    i as u16 * CARD_PRINT_WIDTH + 1, 
-                    1, 
-                    top_card, 
+                    screen,
+                    x,
+                    y,
+                    top_card_opt,
+                    Some(Suit::ALL[i]),
                     top_card_is_highlighted, 
                     self.selected_card_opt == Some(i),
                     self.high_contrast
-                )?;
+                );
             } else if i < FOUNDATIONS + FREE_CELLS {
                 // Print free cells
-                #[allow(clippy::cast_possible_truncation)]
                 Game::print_card_at_coord(
-                    out,
-                    i as u16 * CARD_PRINT_WIDTH + 3,
-                    1,
-                    top_card,
+                    screen,
+                    x,
+                    y,
+                    top_card_opt,
+                    None,
                     top_card_is_highlighted,
                     self.selected_card_opt == Some(i),
                     self.high_contrast
-                )?;
+                );
             } else if i < FOUNDATIONS + FREE_CELLS + TABLEAU_SIZE {
                 // Print tableau column card-by-card
-                let mut card_stack_iter = stack.iter().enumerate().peekable();
-                while let Some((y, &card)) = card_stack_iter.next() {
-                    let is_top_card = card_stack_iter.peek().is_none(); // Check if we are currently printing the top card
+                // The cursor and the selection cover the cards of the sequence they took in, counted from the top card
+                for (depth, &card) in stack.iter().enumerate() {
+                    let cards_from_top = stack.len() - depth;
                     #[allow(clippy::cast_possible_truncation)]
                     Game::print_card_at_coord(
-                        out,
-                        (i as u16 - (FOUNDATIONS as u16 + FREE_CELLS as u16)) * CARD_PRINT_WIDTH + 2,
-                        y as u16 * TABLEAU_VERTICAL_OFFSET + CARD_PRINT_HEIGHT + 1,
-                        card,
-                        top_card_is_highlighted && is_top_card,
-                        self.selected_card_opt == Some(i) && is_top_card,
+                        screen,
+                        x,
+                        y + depth as u16 * TABLEAU_VERTICAL_OFFSET,
+                        Some(card),
+                        None,
+                        top_card_is_highlighted && cards_from_top <= self.highlighted_depth,
+                        self.selected_card_opt == Some(i) && cards_from_top <= self.selected_depth,
                         self.high_contrast,
-                    )?;
+                    );
                 }
                 // If tableau column is empty, print placeholder instead
                 if stack.is_empty() {
-                    #[allow(clippy::cast_possible_truncation)]
                     Game::print_card_at_coord(
-                        out,
-                        (i as u16 - (FOUNDATIONS as u16 + FREE_CELLS as u16)) * CARD_PRINT_WIDTH + 2,
-                        CARD_PRINT_HEIGHT + 1,
-                        top_card,
+                        screen,
+                        x,
+                        y,
+                        None,
+                        None,
                         top_card_is_highlighted,
                         self.selected_card_opt == Some(i),
                         self.high_contrast
-                    )?;
+                    );
                 }
             }
         }
+    }
 
-        Ok(())
+    /// Returns the terminal coordinates of the top left corner of a stack's first card, as printed by `print_board`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn stack_origin(index: usize) -> (u16, u16) {
+        let index = index as u16;
+        let free_cells_start = FOUNDATIONS as u16;
+        let tableau_start = (FOUNDATIONS + FREE_CELLS) as u16;
+        if index < free_cells_start {
+            (index * CARD_PRINT_WIDTH + 1, 1)
+        } else if index < tableau_start {
+            (index * CARD_PRINT_WIDTH + 3, 1)
+        } else {
+            ((index - tableau_start) * CARD_PRINT_WIDTH + 2, CARD_PRINT_HEIGHT + 1)
+        }
+    }
+
+    /// Finds the stack printed at the given terminal coordinates, for example where the player clicked.
+    /// A tableau column reaches from its first card all the way down to the bottom bar, which is the last row of a terminal
+    /// of the given height, so that cards can be dropped below a short column.
+    ///
+    /// # Returns
+    ///
+    /// The index of the stack on the game field, or `None` if there is no stack at the coordinates.
+    #[must_use]
+    pub fn stack_at_coord(x: u16, y: u16, height: u16) -> Option<usize> {
+        let bottom = height.saturating_sub(1);
+        (0..FIELD_SIZE).find(|&i| {
+            let (stack_x, stack_y) = Game::stack_origin(i);
+            let stack_bottom = if i >= FOUNDATIONS + FREE_CELLS {bottom} else {stack_y + CARD_PRINT_HEIGHT};
+            (stack_x..stack_x + CARD_PRINT_WIDTH).contains(&x) && (stack_y..stack_bottom).contains(&y)
+        })
     }
 
-    /// Prints the game chrome (title, side bars, etc.) to the terminal.
-    fn print_chrome(out: &mut std::io::Stdout, move_count: u32) -> Result<(), io::Error> {
-        let (_term_width, term_height) = terminal::size().unwrap_or((DEFAULT_TERMINAL_WIDTH, DEFAULT_TERMINAL_HEIGHT));
-        
-        // Print title bar
-        out.queue(cursor::MoveTo(0, 0))?;
-        print!("╭── Rusty FreeCell ────────────────────────────────────────╮");
-        out.queue(cursor::MoveTo(40, 0))?;
-        print!(" Moves: {move_count} ");
+    /// Draws the game chrome (title, side bars, etc.) on the screen.
+    /// The title bar shows the deal, the moves, the play time and whether auto-play is on,
+    /// and the bottom bar names the keys of the most important actions that fit.
+    fn print_chrome(&self, screen: &mut Screen<impl Write>, bindings: &KeyBindings) {
+        let bottom = screen.height().saturating_sub(1);
+
+        // Print title bar, leaving out the name of the game when a long seed or move count leaves no room for it
+        let max_title_width = MIN_TERMINAL_WIDTH as usize - 7;
+        let mut title_parts = vec!["Rusty FreeCell".to_owned()];
+        title_parts.extend(self.deal.map(|deal| deal.to_string()));
+        title_parts.push(format!("Moves: {}", self.move_count));
+        title_parts.push(format_time(self.elapsed_time()));
+        if self.auto_play {
+            title_parts.push("Auto".to_owned());
+        }
+        let mut title = title_parts.join(" ─ ");
+        if title.chars().count() > max_title_width {
+            title = title_parts[1..].join(" ─ ");
+        }
+        let title: String = title.chars().take(max_title_width).collect();
+        let padding = "─".repeat(max_title_width - title.chars().count());
+        screen.put_str(0, 0, &format!("╭── {title} {padding}─╮"), CellStyle::default());
 
         // Print side bars
 
-        for i in 1..term_height {
-            out.queue(cursor::MoveTo(0, i))?;
-            print!("│");
-            out.queue(cursor::MoveTo(crate::MIN_TERMINAL_WIDTH - 1, i))?;
-            print!("│");
+        for i in 1..bottom {
+            screen.put_str(0, i, "│", CellStyle::default());
+            screen.put_str(MIN_TERMINAL_WIDTH - 1, i, "│", CellStyle::default());
         }
 
-        // Print bottom bar
-        out.queue(cursor::MoveTo(0, term_height))?;
-        print!("╰── (New Game: ctrl-n) ─ (Undo: z) ─ (Quit: ctrl-q) ───────╯");
+        // Print bottom bar, which doubles as the status line while there is a message for the player, flashing it when it is new
+        let max_message_width = MIN_TERMINAL_WIDTH as usize - 6;
+        let message = self.status_message().map_or_else(|| Game::bottom_bar_hints(bindings, max_message_width), str::to_owned);
+        let message: String = message.chars().take(max_message_width).collect();
+        let padding = "─".repeat(max_message_width - message.chars().count());
+        let flashing = self.status_message_opt.as_ref().is_some_and(|(_, shown)| shown.elapsed() < STATUS_MESSAGE_FLASH_TIME);
+        screen.put_str(0, bottom, &format!("╰─ {message} {padding}─╯"), CellStyle::default());
+        screen.put_str(3, bottom, &message, CellStyle {reverse: flashing, ..CellStyle::default()});
+    }
 
-        Ok(())
+    /// Lists the keys of the actions named in the bottom bar, such as "(New: ctrl-n) ─ (Help: f1)",
+    /// leaving out the least important ones that do not fit in the given width, and the ones without a key.
+    fn bottom_bar_hints(bindings: &KeyBindings, width: usize) -> String {
+        let hints: Vec<Option<String>> = BOTTOM_BAR_COMMANDS.iter()
+            .map(|&(command, label)| bindings.keys_for(command).first().map(|chord| format!("({label}: {chord})")))
+            .collect();
+        let separator = " ─ ";
+        let mut shown = [false; BOTTOM_BAR_COMMANDS.len()];
+        let mut used_width = 0;
+        for i in BOTTOM_BAR_PRIORITY {
+            let Some(hint) = &hints[i] else {continue};
+            let hint_width = hint.chars().count() + if used_width == 0 {0} else {separator.chars().count()};
+            if used_width + hint_width <= width {
+                shown[i] = true;
+                used_width += hint_width;
+            }
+        }
+        let shown_hints: Vec<&str> = hints.iter().zip(shown)
+            .filter_map(|(hint, is_shown)| hint.as_deref().filter(|_| is_shown))
+            .collect();
+        shown_hints.join(separator)
     }
 
-    /// Prints a card at the specified coordinates on the terminal.
-    fn print_card_at_coord(out: &mut io::Stdout, x: u16, y: u16, card: Card, highlighted: bool, selected: bool, high_contrast: bool)  -> Result<(), io::Error> {
-        let card_suit_rank_str = RANK_STRINGS[card.rank as usize].to_owned() + SUIT_STRINGS[card.suit as usize];
+    /// Draws a card at the specified coordinates on the screen, or a placeholder if the slot is empty.
+    /// The placeholder of an empty foundation is decorated with the foundation's suit.
+    #[allow(clippy::too_many_arguments)]
+    fn print_card_at_coord(screen: &mut Screen<impl Write>, x: u16, y: u16, card_opt: Option<Card>, placeholder_suit_opt: Option<Suit>, highlighted: bool, selected: bool, high_contrast: bool) {
+        let card_suit_rank_str = match (card_opt, placeholder_suit_opt) {
+            (Some(card), _) => format!("{}{}", card.rank.symbol(), card.suit.symbol()),
+            (None, Some(suit)) => format!(" {}", suit.symbol()),
+            (None, None) => "  ".to_owned()
+        };
         let card_display_str;
         if selected {
+            // The mark sits on the first line below the border, which stays visible when the card is covered by others
             card_display_str= format!("\
                 ╭─────╮\n\
-                │ {card_suit_rank_str: <3} │\n\
+                │ {card_suit_rank_str: <3}△│\n\
+                │     │\n\
                 │     │\n\
-                │  △  │\n\
                 ╰─────╯\n");
-        } else if card.rank == 0 {
+        } else if card_opt.is_none() {
             // Print suit-decorated placeholder
             card_display_str= format!("\
             ╭─────╮\n\
@@ -178,81 +399,172 @@ impl Game {
             ╰─────╯\n");
         }
 
+        let color_opt = card_opt.and_then(|card| if high_contrast {
+            Some(match card.suit {
+                Suit::Hearts => style::Color::DarkRed,
+                Suit::Clubs => style::Color::White,
+                Suit::Diamonds => style::Color::Magenta,
+                Suit::Spades => style::Color::Yellow
+            })
+        } else {
+            match card.color() {
+                Color::Red => Some(style::Color::Red),
+                Color::Black => None
+            }
+        });
+        // Placeholders are dimmed, unless they are highlighted
+        let card_style = CellStyle {color_opt, reverse: highlighted, dim: card_opt.is_none() && !highlighted};
         for (d, line) in card_display_str.lines().enumerate() {
             #[allow(clippy::cast_possible_truncation)]
-            out.queue(cursor::MoveTo(x, y + d as u16))?;
-            if highlighted {
-                let _= out.queue(style::SetAttribute(style::Attribute::Reverse));
-            } else if card.rank == 0 {
-                // dim placeholder
-                let _= out.queue(style::SetAttribute(style::Attribute::Dim));
-            }
-
-            if card.rank != 0 {
-                if high_contrast {
-                    match card.suit {
-                        HEARTS => {
-                            print!("{}", line.with(style::Color::DarkRed));
-                        },
-                        CLUBS => {
-                            print!("{}", line.with(style::Color::White));
-                        },
-                        DIAMONDS => {
-                            print!("{}", line.with(style::Color::Magenta));
-                        },
-                        SPADES => {
-                            print!("{}", line.with(style::Color::Yellow));
-                        },
-                        _ => {
-                            print!("{line}");
-                        }
-                    }
-                } else {
-                    match card.suit {
-                        HEARTS | DIAMONDS  => {
-                            print!("{}", line.with(style::Color::Red));
-                        },
-                        _ => {
-                            print!("{line}");
-                        }
-                    }
-                }
-            } else {
-                print!("{line}");
-            }
-
-            if highlighted {
-                let _= out.queue(style::SetAttribute(style::Attribute::NoReverse));
-            } else if card.rank == 0 {
-                // undim placeholder
-                let _= out.queue(style::SetAttribute(style::Attribute::NormalIntensity));
-            }
+            screen.put_str(x, y + d as u16, line, card_style);
         }
-        Ok(())
     }
 
-    /// Prints the win message to the terminal.
-    fn print_win (out: &mut io::Stdout) -> Result<(), io::Error> {
+    /// Draws the win message on the screen.
+    fn print_win (screen: &mut Screen<impl Write>, hints_used: u32, bindings: &KeyBindings) {
         let win_message_width = 20;
-        let win_message_height = 4;
-        Game::print_string_at_coord(out,   
-        "╭──────────────────╮\n\
+        let win_message_height = 5;
+        let new_game_hint: String = format!("New Game: {}", key_name(bindings, Command::NewGame)).chars().take(16).collect();
+        Game::print_string_at_coord(screen,
+        &format!("╭──────────────────╮\n\
                  │ You Win!         │\n\
-                 │ New Game: ctrl-n │\n\
-                 ╰──────────────────╯",
+                 │ Hints used: {hints_used: <4} │\n\
+                 │ {new_game_hint: <16} │\n\
+                 ╰──────────────────╯"),
                 MIN_TERMINAL_WIDTH / 2 - win_message_width / 2,
-                TYPICAL_BOARD_HEIGHT / 2 - win_message_height / 2)?;
-        Ok(())
+                TYPICAL_BOARD_HEIGHT / 2 - win_message_height / 2);
+    }
+
+    /// Draws the message shown when there are no more moves on the screen.
+    fn print_stalemate (screen: &mut Screen<impl Write>, bindings: &KeyBindings) {
+        let stalemate_message_width = 20;
+        let stalemate_message_height = 5;
+        let undo_hint: String = format!("Undo: {}", key_name(bindings, Command::Undo)).chars().take(16).collect();
+        let new_game_hint: String = format!("New Game: {}", key_name(bindings, Command::NewGame)).chars().take(16).collect();
+        Game::print_string_at_coord(screen,
+        &format!("╭──────────────────╮\n\
+                 │ No more moves!   │\n\
+                 │ {undo_hint: <16} │\n\
+                 │ {new_game_hint: <16} │\n\
+                 ╰──────────────────╯"),
+                MIN_TERMINAL_WIDTH / 2 - stalemate_message_width / 2,
+                TYPICAL_BOARD_HEIGHT / 2 - stalemate_message_height / 2);
     }
 
-    /// Prints a string at the specified coordinates on the terminal.
-    fn print_string_at_coord(out: &mut io::Stdout, string: &str, x: u16, y: u16) -> Result<(), io::Error> {
+    /// Prints a box of lines over the dimmed game state, just below the title bar.
+    /// The cells around the box are blanked up to the title and bottom bars, so that no dimmed card borders run into its frame.
+    /// Lines that do not fit above the bottom bar are left out, which only happens on screens shorter than the minimum.
+    fn print_overlay_box(screen: &mut Screen<impl Write>, lines: &[String]) {
+        let (x, y) = (2, 1);
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (lines.len() as u16).min(screen.height().saturating_sub(y + 1));
+        let margin = " ".repeat(OVERLAY_WIDTH + 2);
+        for margin_y in y..(y + height + 1).min(screen.height().saturating_sub(1)) {
+            screen.put_str(x - 1, margin_y, &margin, CellStyle::default());
+        }
+        Game::print_string_at_coord(screen, &lines[..usize::from(height)].join("\n"), x, y);
+    }
+
+    /// Draws a string at the specified coordinates on the screen, one line below the other.
+    fn print_string_at_coord(screen: &mut Screen<impl Write>, string: &str, x: u16, y: u16) {
         for (i, line) in string.lines().enumerate() {
             #[allow(clippy::cast_possible_truncation)]
-            out.queue(cursor::MoveTo(x, y + i as u16))?;
-            print!("{line}");
+            screen.put_str(x, y + i as u16, line, CellStyle::default());
         }
-        Ok(())
     }
 }
 
+/// Returns the bottom border of the help and statistics screens, which tells how to close them.
+fn overlay_bottom_border(inner_width: usize) -> String {
+    let label = "─ Press any key to close ";
+    format!("╰{label}{}╯", "─".repeat(inner_width + 2 - label.chars().count()))
+}
+
+/// Returns the name of the main key bound to an action, or "none" if it has no key.
+fn key_name(bindings: &KeyBindings, command: Command) -> String {
+    bindings.keys_for(command).first().map_or("none".to_owned(), ToString::to_string)
+}
+
+/// Formats a play time as minutes and seconds, such as "4:05", with the hours in front from the first hour on, such as "1:04:05".
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::Game, keys::KeyBindings};
+
+    use super::{screen::Screen, MIN_TERMINAL_HEIGHT, MIN_TERMINAL_WIDTH};
+
+    #[test]
+    fn deal_prints_the_same() {
+        let mut screen = Screen::new(Vec::new(), MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT);
+        Game::from_deal_number(1).print(&mut screen, &KeyBindings::default()).unwrap();
+        assert_eq!(screen.to_string(), "\
+            ╭── Rusty FreeCell ─ Game #1 ─ Moves: 0 ─ 0:00 ────────────╮\n\
+            │╭─────╮╭─────╮╭─────╮╭─────╮  ╭─────╮╭─────╮╭─────╮╭─────╮│\n\
+            ││     ││     ││     ││     │  │     ││     ││     ││     ││\n\
+            ││  ♥  ││  ♣  ││  ♦  ││  ♠  │  │     ││     ││     ││     ││\n\
+            ││     ││     ││     ││     │  │     ││     ││     ││     ││\n\
+            │╰─────╯╰─────╯╰─────╯╰─────╯  ╰─────╯╰─────╯╰─────╯╰─────╯│\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ J♦  ││ 2♦  ││ 9♥  ││ J♣  ││ 5♦  ││ 7♥  ││ 7♣  ││ 5♥  │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ K♦  ││ K♣  ││ 9♠  ││ 5♠  ││ A♦  ││ Q♣  ││ K♥  ││ 3♥  │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ 2♠  ││ K♠  ││ 9♦  ││ Q♦  ││ J♠  ││ A♠  ││ A♥  ││ 3♣  │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ 4♣  ││ 5♣  ││ 10♠ ││ Q♥  ││ 4♥  ││ A♣  ││ 4♦  ││ 7♠  │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ 3♠  ││ 10♦ ││ 4♠  ││ 10♥ ││ 8♥  ││ 2♣  ││ J♥  ││ 7♦  │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮╭─────╮ │\n\
+            │ │ 6♦  ││ 8♠  ││ 8♦  ││ Q♠  ││ 6♣  ││ 3♦  ││ 8♣  ││ 10♣ │ │\n\
+            │ ╭─────╮╭─────╮╭─────╮╭─────╮│     ││     ││     ││     │ │\n\
+            │ │ 6♠  ││ 9♣  ││ 2♥  ││ 6♥  ││     ││     ││     ││     │ │\n\
+            │ │     ││     ││     ││     │╰─────╯╰─────╯╰─────╯╰─────╯ │\n\
+            │ │     ││     ││     ││     │                             │\n\
+            │ ╰─────╯╰─────╯╰─────╯╰─────╯                             │\n\
+            ╰─ (New: ctrl-n) ─ (Undo: z) ─ (Hint: ?) ─ (Help: f1) ─────╯\n");
+        // The frame went to the output stream as well
+        assert!(!screen.get_ref().is_empty());
+    }
+
+    #[test]
+    fn help_fits_above_bottom_bar() {
+        let mut screen = Screen::new(Vec::new(), MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT);
+        Game::from_deal_number(1).print_help(&mut screen, &KeyBindings::default()).unwrap();
+        let screenshot = screen.to_string();
+        let lines: Vec<&str> = screenshot.lines().collect();
+        assert_eq!(lines.len(), usize::from(MIN_TERMINAL_HEIGHT));
+        // Leave at least one spare row, so that one more action does not push the help into the bottom bar
+        let bottom_border = lines.iter().position(|line| line.starts_with("│ ╰─ Press any key to close ─")).unwrap();
+        assert!(bottom_border <= lines.len() - 3);
+        assert!(lines[lines.len() - 1].starts_with("╰─ (New: ctrl-n) ─ (Undo: z) ─ (Hint: ?) ─ (Help: f1) ─"));
+    }
+
+    #[test]
+    fn clicks_find_the_stack_under_them() {
+        let height = MIN_TERMINAL_HEIGHT;
+        // Foundations and free cells, with the gap between them and their bottom edge
+        assert_eq!(Game::stack_at_coord(1, 1, height), Some(0));
+        assert_eq!(Game::stack_at_coord(28, 5, height), Some(3));
+        assert_eq!(Game::stack_at_coord(29, 3, height), None);
+        assert_eq!(Game::stack_at_coord(31, 1, height), Some(4));
+        assert_eq!(Game::stack_at_coord(58, 5, height), Some(7));
+        // Tableau columns reach down to the row above the bottom bar, between the side bars
+        assert_eq!(Game::stack_at_coord(2, 6, height), Some(8));
+        assert_eq!(Game::stack_at_coord(57, 22, height), Some(15));
+        assert_eq!(Game::stack_at_coord(9, 40, 50), Some(9));
+        assert_eq!(Game::stack_at_coord(1, 10, height), None);
+        assert_eq!(Game::stack_at_coord(58, 10, height), None);
+        // Neither the title bar nor the bottom bar is a stack
+        assert_eq!(Game::stack_at_coord(2, 0, height), None);
+        assert_eq!(Game::stack_at_coord(2, 23, height), None);
+        assert_eq!(Game::stack_at_coord(9, 49, 50), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! An off-screen buffer of terminal cells that the game is drawn into, so that only what changed since the last frame is sent to the terminal
+
+use std::{fmt, io::{self, Write}};
+
+use crossterm::{cursor, style, terminal, QueueableCommand};
+
+/// How the text in a cell is printed.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct CellStyle {
+    /// The color of the text, or `None` for the terminal's default color.
+    pub color_opt: Option<style::Color>,
+
+    /// Whether the text and background colors are swapped.
+    pub reverse: bool,
+
+    /// Whether the text is printed faint.
+    pub dim: bool,
+}
+
+/// One character on the terminal, along with how it is printed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Cell {
+    /// The character printed in the cell.
+    symbol: char,
+
+    /// How the character is printed.
+    style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {symbol: ' ', style: CellStyle::default()}
+    }
+}
+
+/// A terminal screen of a given size, drawn a frame at a time.
+/// Each frame is drawn into an off-screen buffer between `begin_frame` and `end_frame`,
+/// which compares it with the frame before and only prints the cells that changed.
+/// The frames can be printed to any output stream, such as the standard output or a buffer in memory.
+pub struct Screen<W: Write> {
+    /// The output stream the frames are printed to.
+    out: W,
+
+    /// The width of the screen in cells.
+    width: u16,
+
+    /// The height of the screen in cells.
+    height: u16,
+
+    /// The frame being drawn, row by row.
+    cells: Vec<Cell>,
+
+    /// The frame the terminal shows, row by row, or `None` if what it shows is unknown and it has to be printed in full.
+    shown_opt: Option<Vec<Cell>>,
+}
+
+impl<W: Write> Screen<W> {
+    /// Creates a screen of the given size in cells that prints to the given output stream.
+    /// It starts out printing the first frame in full.
+    #[must_use]
+    pub fn new(out: W, width: u16, height: u16) -> Screen<W> {
+        Screen {
+            out,
+            width,
+            height,
+            cells: Vec::new(),
+            shown_opt: None
+        }
+    }
+
+    /// Returns a reference to the output stream the frames are printed to.
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Returns the output stream the frames are printed to, giving up the screen.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Returns the width of the frame in cells.
+    #[must_use]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Returns the height of the frame in cells.
+    #[must_use]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Changes the size of the screen in cells, such as when the terminal was resized.
+    /// If the size is different, the next frame is printed in full.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.shown_opt = None;
+        }
+    }
+
+    /// Starts drawing a new frame on a blank screen.
+    pub fn begin_frame(&mut self) {
+        self.cells.clear();
+        self.cells.resize(usize::from(self.width) * usize::from(self.height), Cell::default());
+    }
+
+    /// Draws a string on one row of the frame, starting at the given coordinates, cutting off whatever does not fit.
+    pub fn put_str(&mut self, x: u16, y: u16, string: &str, style: CellStyle) {
+        if y >= self.height {return;}
+        let row_start = usize::from(y) * usize::from(self.width);
+        for (column, symbol) in (usize::from(x)..usize::from(self.width)).zip(string.chars()) {
+            self.cells[row_start + column] = Cell {symbol, style};
+        }
+    }
+
+    /// Dims everything drawn in the frame so far, as a background for whatever is drawn over it next.
+    pub fn dim_all(&mut self) {
+        for cell in &mut self.cells {
+            cell.style.dim = true;
+        }
+    }
+
+    /// Finishes the frame, printing the cells that differ from the frame the terminal shows.
+    /// Neighboring changed cells with the same style are printed in one go, with the style set once before them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if writing to the output stream fails.
+    pub fn end_frame(&mut self) -> Result<(), io::Error> {
+        let shown = if let Some(shown) = self.shown_opt.take() {
+            shown
+        } else {
+            self.out.queue(terminal::Clear(terminal::ClearType::All))?;
+            vec![Cell::default(); self.cells.len()]
+        };
+
+        self.out.queue(style::SetAttribute(style::Attribute::Reset))?;
+        let mut current_style = CellStyle::default();
+        // Where the terminal's cursor is after the last printed cell, if it is known
+        let mut cursor_opt = None;
+        let mut run = String::new();
+        for (i, (&cell, &shown_cell)) in self.cells.iter().zip(&shown).enumerate() {
+            if cell == shown_cell {continue;}
+            #[allow(clippy::cast_possible_truncation)]
+            let (x, y) = ((i % usize::from(self.width)) as u16, (i / usize::from(self.width)) as u16);
+            if cursor_opt != Some((x, y)) || cell.style != current_style {
+                if !run.is_empty() {
+                    self.out.queue(style::Print(&run))?;
+                    run.clear();
+                }
+                if cursor_opt != Some((x, y)) {
+                    self.out.queue(cursor::MoveTo(x, y))?;
+                }
+                if cell.style != current_style {
+                    queue_style(&mut self.out, cell.style)?;
+                    current_style = cell.style;
+                }
+            }
+            run.push(cell.symbol);
+            // The cursor stays put after the last column, so the next cell needs a move either way
+            cursor_opt = (x + 1 < self.width).then_some((x + 1, y));
+        }
+        self.out.queue(style::Print(&run))?;
+        self.out.queue(style::SetAttribute(style::Attribute::Reset))?;
+        self.out.flush()?;
+
+        self.shown_opt = Some(self.cells.clone());
+        Ok(())
+    }
+}
+
+/// Writes the characters of the last frame without their styles, one line per row with the spaces at the end left out,
+/// such as for a screenshot of the game.
+impl<W: Write> fmt::Display for Screen<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.width == 0 {return Ok(());}
+        for row in self.cells.chunks(usize::from(self.width)) {
+            let line: String = row.iter().map(|cell| cell.symbol).collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets the style of the text printed next, starting from the default style.
+fn queue_style(out: &mut impl Write, cell_style: CellStyle) -> Result<(), io::Error> {
+    out.queue(style::SetAttribute(style::Attribute::Reset))?;
+    if cell_style.reverse {
+        out.queue(style::SetAttribute(style::Attribute::Reverse))?;
+    }
+    if cell_style.dim {
+        out.queue(style::SetAttribute(style::Attribute::Dim))?;
+    }
+    if let Some(color) = cell_style.color_opt {
+        out.queue(style::SetForegroundColor(color))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::{cursor, style, terminal, QueueableCommand};
+
+    use super::{CellStyle, Screen};
+
+    /// Draws a frame with a line of text on the screen, returning just what printing it wrote to the output stream.
+    fn print_frame(screen: &mut Screen<Vec<u8>>, text: &str) -> Vec<u8> {
+        let written_before = screen.get_ref().len();
+        screen.begin_frame();
+        screen.put_str(0, 1, text, CellStyle::default());
+        screen.end_frame().unwrap();
+        screen.get_ref()[written_before..].to_vec()
+    }
+
+    #[test]
+    fn unchanged_frame_prints_nothing_but_the_style_resets() {
+        let mut screen = Screen::new(Vec::new(), 10, 3);
+        print_frame(&mut screen, "Hello");
+        let mut expected = Vec::new();
+        expected.queue(style::SetAttribute(style::Attribute::Reset)).unwrap();
+        expected.queue(style::SetAttribute(style::Attribute::Reset)).unwrap();
+        assert_eq!(print_frame(&mut screen, "Hello"), expected);
+    }
+
+    #[test]
+    fn changed_cell_is_printed_alone() {
+        let mut screen = Screen::new(Vec::new(), 10, 3);
+        print_frame(&mut screen, "Hello");
+        let mut expected = Vec::new();
+        expected.queue(style::SetAttribute(style::Attribute::Reset)).unwrap();
+        expected.queue(cursor::MoveTo(1, 1)).unwrap();
+        expected.queue(style::Print("a")).unwrap();
+        expected.queue(style::SetAttribute(style::Attribute::Reset)).unwrap();
+        assert_eq!(print_frame(&mut screen, "Hallo"), expected);
+    }
+
+    #[test]
+    fn resize_prints_the_next_frame_in_full() {
+        let mut clear = Vec::new();
+        clear.queue(terminal::Clear(terminal::ClearType::All)).unwrap();
+        let mut screen = Screen::new(Vec::new(), 10, 3);
+        assert!(print_frame(&mut screen, "Hello").starts_with(&clear));
+
+        // Resizing to the same size keeps what the terminal shows
+        screen.resize(10, 3);
+        assert!(!print_frame(&mut screen, "Hello").starts_with(&clear));
+
+        screen.resize(12, 3);
+        let output = print_frame(&mut screen, "Hello");
+        assert!(output.starts_with(&clear));
+        assert!(String::from_utf8(output).unwrap().contains("Hello"));
+        assert_eq!(screen.to_string(), "\nHello\n\n");
+    }
+}
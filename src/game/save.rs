@@ -0,0 +1,302 @@
+//! Saving the state of the `FreeCell` game to disk and loading it back
+
+use std::{fmt, fs, io, path::{Path, PathBuf}, time::Duration};
+
+use crate::cards::Card;
+
+use super::{Action, Deal, Game, Move, MoveCounting, PositionError, FIELD_SIZE};
+
+/// The first line of every save file.
+const SAVE_FILE_HEADER: &str = "rusty-freecell save";
+/// The version of the save file format written and read by this version of the game.
+const SAVE_FORMAT_VERSION: u32 = 1;
+/// The name of the save file inside the game's data directory.
+const SAVE_FILE_NAME: &str = "save.txt";
+
+/// An error that occurred while loading a saved game, or the statistics kept in `stats`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file was written in a format version this version of the game does not understand.
+    /// Holds the version found and the version that is understood.
+    UnsupportedVersion(u32, u32),
+    /// A line of the file could not be understood.
+    Malformed(String),
+    /// The saved game does not hold a valid position, such as when it is missing some of the 52 cards.
+    InvalidPosition(PositionError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{error}"),
+            LoadError::UnsupportedVersion(version, expected) => write!(f, "format version {version} is not supported, expected version {expected}"),
+            LoadError::Malformed(problem) => write!(f, "malformed file: {problem}"),
+            LoadError::InvalidPosition(error) => write!(f, "invalid position: {error}")
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+/// Returns the game's data directory, `$XDG_DATA_HOME/rusty-freecell/`, or `~/.local/share/rusty-freecell/` if that is not set.
+///
+/// # Returns
+///
+/// The path of the data directory, or `None` if neither `$XDG_DATA_HOME` nor `$HOME` is set.
+pub fn data_dir_path() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))?;
+    Some(data_home.join("rusty-freecell"))
+}
+
+/// Writes a file by writing a temporary file next to it and renaming that over it, creating its directory if needed,
+/// so that quitting or crashing halfway through never leaves a half-written file behind.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if the file or its directory cannot be written.
+pub fn write_file_atomically(path: &Path, contents: &str) -> Result<(), io::Error> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let mut temp_file_name = path.file_name().ok_or(io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?.to_owned();
+    temp_file_name.push(".tmp");
+    let temp_file_path = path.with_file_name(temp_file_name);
+    fs::write(&temp_file_path, contents)?;
+    fs::rename(&temp_file_path, path)
+}
+
+/// Returns the path of the save file inside the game's data directory, see `data_dir_path`.
+///
+/// # Returns
+///
+/// The path of the save file, or `None` if neither `$XDG_DATA_HOME` nor `$HOME` is set.
+#[must_use]
+pub fn save_file_path() -> Option<PathBuf> {
+    Some(data_dir_path()?.join(SAVE_FILE_NAME))
+}
+
+impl Game {
+    /// Saves the full game state, including the undo and redo history, to a file, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file or its directory cannot be written.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), io::Error> {
+        write_file_atomically(path, &self.to_save_string())
+    }
+
+    /// Loads a game previously written by `save_to_file`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LoadError` if the file cannot be read, is malformed, or does not hold a valid position with all 52 cards.
+    pub fn load_from_file(path: &Path) -> Result<Game, LoadError> {
+        Game::from_save_string(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the game state in the save file format.
+    fn to_save_string(&self) -> String {
+        let mut lines = vec![SAVE_FILE_HEADER.to_owned(), format!("version {SAVE_FORMAT_VERSION}")];
+        match self.deal {
+            Some(Deal::Numbered(deal_number)) => lines.push(format!("deal number {deal_number}")),
+            Some(Deal::Seeded(seed)) => lines.push(format!("deal seed {seed}")),
+            None => {}
+        }
+        lines.push(format!("moves {}", self.move_count));
+        lines.push(match self.move_counting {
+            MoveCounting::EachCard => "counting each-card".to_owned(),
+            MoveCounting::EachAction => "counting each-action".to_owned()
+        });
+        lines.push(format!("hints {}", self.hints_used));
+        lines.push(format!("time {}", self.elapsed_time().as_millis()));
+        for stack in &self.field {
+            let cards: Vec<String> = stack.iter().map(|&card| card.to_string()).collect();
+            lines.push(format!("stack {}", cards.join(" ")).trim_end().to_owned());
+        }
+        for undo_action in &self.undo_history {
+            lines.push(format!("undo {}", action_to_string(undo_action)));
+        }
+        for redo_action in &self.redo_history {
+            lines.push(format!("redo {}", action_to_string(redo_action)));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads a game state written in the save file format, checking that it holds a valid position.
+    fn from_save_string(contents: &str) -> Result<Game, LoadError> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(SAVE_FILE_HEADER) {
+            return Err(LoadError::Malformed("not a rusty-freecell save file".to_owned()));
+        }
+        let version = lines.next()
+            .and_then(|line| line.strip_prefix("version "))
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or(LoadError::Malformed("missing format version".to_owned()))?;
+        if version != SAVE_FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion(version, SAVE_FORMAT_VERSION));
+        }
+
+        let mut game = Game::deal_deck(Vec::new(), None);
+        let mut stacks_read = 0;
+        for line in lines {
+            let malformed = || LoadError::Malformed(format!("cannot read line \"{line}\""));
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "deal" => {
+                    game.deal = Some(match value.split_once(' ').ok_or_else(malformed)? {
+                        ("number", deal_number) => Deal::Numbered(deal_number.parse().map_err(|_| malformed())?),
+                        ("seed", seed) => Deal::Seeded(seed.parse().map_err(|_| malformed())?),
+                        _ => return Err(malformed())
+                    });
+                },
+                "moves" => {
+                    game.move_count = value.parse().map_err(|_| malformed())?;
+                },
+                "counting" => {
+                    game.move_counting = match value {
+                        "each-card" => MoveCounting::EachCard,
+                        "each-action" => MoveCounting::EachAction,
+                        _ => return Err(malformed())
+                    };
+                },
+                "hints" => {
+                    game.hints_used = value.parse().map_err(|_| malformed())?;
+                },
+                "time" => {
+                    // The clock starts again with the next move
+                    game.elapsed_time = Duration::from_millis(value.parse().map_err(|_| malformed())?);
+                },
+                "stack" => {
+                    let stack = game.field.get_mut(stacks_read).ok_or_else(malformed)?;
+                    for token in value.split_whitespace() {
+                        stack.push(token.parse::<Card>().map_err(|_| malformed())?);
+                    }
+                    stacks_read += 1;
+                },
+                "undo" | "redo" => {
+                    let action = action_from_string(value).ok_or_else(malformed)?;
+                    let history = if key == "undo" {&mut game.undo_history} else {&mut game.redo_history};
+                    history.push_back(action);
+                },
+                _ => {
+                    return Err(malformed());
+                }
+            }
+        }
+        if stacks_read != FIELD_SIZE {
+            return Err(LoadError::Malformed(format!("expected {FIELD_SIZE} stacks, found {stacks_read}")));
+        }
+
+        game.check_position().map_err(LoadError::InvalidPosition)?;
+        game.check_history()?;
+        game.update_stalemate();
+        Ok(game)
+    }
+
+    /// Checks that every action of the undo and redo history can be played back from the current position,
+    /// by taking back the undo history and then playing the redo history on a copy of the game.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LoadError` if a move of the history needs more cards than its source stack holds,
+    /// or leads to an invalid position, such as one with two cards in a free cell.
+    fn check_history(&self) -> Result<(), LoadError> {
+        // Undo takes back the most recent action first
+        let mut position = self.clone();
+        for action in self.undo_history.iter().rev() {
+            let taken_back_moves = action.iter().rev().map(|undone_move| Move{from: undone_move.to, to: undone_move.from, count: undone_move.count});
+            position.play_history_action("undo", taken_back_moves)?;
+        }
+        // Redo plays the most recently undone action first, starting from the current position
+        let mut position = self.clone();
+        for action in self.redo_history.iter().rev() {
+            position.play_history_action("redo", action.iter().copied())?;
+        }
+        Ok(())
+    }
+
+    /// Plays the moves of an action from the undo or redo history, checking that they fit the position, see `check_history`.
+    fn play_history_action(&mut self, history_name: &str, moves: impl Iterator<Item = Move>) -> Result<(), LoadError> {
+        for history_move in moves {
+            if self.field[history_move.from].len() < history_move.count {
+                return Err(LoadError::Malformed(format!("the {history_name} history does not fit the position")));
+            }
+            self.execute_move(history_move.from, history_move.to, history_move.count);
+        }
+        self.check_position().map_err(LoadError::InvalidPosition)
+    }
+}
+
+/// Writes the moves of an action as "from to count" triples of field indices, all on one line.
+fn action_to_string(action: &Action) -> String {
+    let moves: Vec<String> = action.iter().map(|action_move| format!("{} {} {}", action_move.from, action_move.to, action_move.count)).collect();
+    moves.join(" ")
+}
+
+/// Reads an action written by `action_to_string`.
+fn action_from_string(string: &str) -> Option<Action> {
+    let numbers = string.split(' ').map(str::parse::<usize>).collect::<Result<Vec<usize>, _>>().ok()?;
+    if numbers.is_empty() || numbers.len() % 3 != 0 {return None;}
+    numbers.chunks(3).map(|triple| {
+        let &[from, to, count] = triple else {return None};
+        (from < FIELD_SIZE && to < FIELD_SIZE && count > 0).then_some(Move{from, to, count})
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_file_atomically, LoadError};
+    use crate::game::{Game, PositionError};
+
+    /// Saves deal #1 after a few moves, with the last one undone.
+    fn save_string() -> String {
+        let mut game = Game::from_deal_number(1);
+        game.replay("1a 2b 3c").unwrap();
+        game.perform_undo();
+        game.to_save_string()
+    }
+
+    #[test]
+    fn saved_game_loads_the_same() {
+        let save = save_string();
+        let loaded = Game::from_save_string(&save).unwrap();
+        assert_eq!(loaded.move_notation().unwrap(), "1a 2b");
+        assert_eq!(loaded.to_save_string(), save);
+    }
+
+    #[test]
+    fn undo_history_that_breaks_the_rules_is_rejected() {
+        // Taking back three cards from the first column into the second free cell would leave it holding three cards
+        let save = save_string() + "undo 5 8 3\n";
+        assert!(matches!(Game::from_save_string(&save), Err(LoadError::InvalidPosition(PositionError::FreeCell(5)))));
+    }
+
+    #[test]
+    fn history_with_missing_cards_is_rejected() {
+        // The third free cell is empty, so there is no card to redo a move from it
+        let save = save_string();
+        assert!(save.contains("redo 10 6 1\n"));
+        let save = save.replace("redo 10 6 1\n", "redo 6 10 1\n");
+        assert!(matches!(Game::from_save_string(&save), Err(LoadError::Malformed(_))));
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_and_leaves_no_temporary_file() {
+        let directory = std::env::temp_dir().join(format!("rusty-freecell-test-{}", std::process::id()));
+        let path = directory.join("save.txt");
+        write_file_atomically(&path, "first").unwrap();
+        write_file_atomically(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
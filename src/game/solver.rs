@@ -0,0 +1,281 @@
+//! Searches `FreeCell` positions for a winning sequence of moves
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::{Duration, Instant}
+};
+
+use super::{Game, Move, FIELD_SIZE, FOUNDATIONS, FREE_CELLS, RANKS};
+
+/// How many positions are examined between checks of the time budget and the cancellation flag.
+const LIMIT_CHECK_INTERVAL: usize = 256;
+
+/// Limits on how much work the solver may do before giving up. The default has no limits at all.
+#[derive(Default, Clone)]
+pub struct SolverLimits {
+    /// The maximum number of positions to examine, if any.
+    pub max_nodes: Option<usize>,
+
+    /// The maximum amount of time to search for, if any.
+    pub time_budget: Option<Duration>,
+
+    /// A flag that can be set from another thread to stop the search early, if any.
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+}
+
+/// The outcome of a search for a winning sequence of moves.
+#[derive(Debug)]
+pub enum Solution {
+    /// The position can be won by playing these moves in order.
+    Solvable(Vec<Move>),
+
+    /// The whole search space was explored without finding a win.
+    Unsolvable,
+
+    /// The search was stopped by its limits before reaching a verdict.
+    Unknown,
+}
+
+/// Searches for a sequence of moves that wins the game from the given position.
+///
+/// The search is a depth-first search over the moves that `move_is_valid` allows, played with `execute_move`,
+/// so every solution can be replayed in the game as is. Moves are tried in order of how promising the position they
+/// lead to looks. Positions that only differ in the order of the free cells or tableau columns are only examined once,
+/// cards are never taken back off the foundations, and cards that are safe to stack are moved to the foundations
+/// right away. Once a solution is found, detours in it are cut short.
+///
+/// # Arguments
+///
+/// * `game` - The position to solve. It is not modified.
+/// * `limits` - The node budget, time budget and cancellation flag of the search.
+///
+/// # Returns
+///
+/// A `Solution` with the winning moves, or whether the position is unsolvable or the search gave up.
+#[must_use]
+pub fn solve(game: &Game, limits: &SolverLimits) -> Solution {
+    let start_time = Instant::now();
+    let mut position = game.clone();
+    let mut visited = HashSet::from([position.position_key()]);
+    let mut path: Vec<Move> = Vec::new();
+    let mut candidate_stack = vec![candidate_moves(&position).into_iter()];
+    let mut nodes = 0;
+
+    while let Some(candidates) = candidate_stack.last_mut() {
+        if position.is_won() {
+            return Solution::Solvable(shorten_solution(game, &path));
+        }
+        if let Some(next_move) = candidates.next() {
+            position.execute_move(next_move.from, next_move.to, next_move.count);
+            if visited.insert(position.position_key()) {
+                nodes += 1;
+                if limits.max_nodes.is_some_and(|max_nodes| nodes > max_nodes) {
+                    return Solution::Unknown;
+                }
+                if nodes % LIMIT_CHECK_INTERVAL == 0 {
+                    let out_of_time = limits.time_budget.is_some_and(|time_budget| start_time.elapsed() > time_budget);
+                    let cancelled = limits.cancel_flag.as_ref().is_some_and(|cancel_flag| cancel_flag.load(Ordering::Relaxed));
+                    if out_of_time || cancelled {
+                        return Solution::Unknown;
+                    }
+                }
+                path.push(next_move);
+                candidate_stack.push(candidate_moves(&position).into_iter());
+            } else {
+                // Already examined this position, take the move back
+                position.execute_move(next_move.to, next_move.from, next_move.count);
+            }
+        } else {
+            // Dead end, backtrack
+            candidate_stack.pop();
+            if let Some(last_move) = path.pop() {
+                position.execute_move(last_move.to, last_move.from, last_move.count);
+            }
+        }
+    }
+
+    Solution::Unsolvable
+}
+
+/// Cuts detours out of a solution by looking for single moves that skip ahead to a later position of the solution.
+fn shorten_solution(game: &Game, solution: &[Move]) -> Vec<Move> {
+    // Positions are told apart exactly here, since later moves of the solution depend on which cell or column holds what
+    let exact_key = |position: &Game| {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::hash::DefaultHasher::new();
+        for stack in &position.field {
+            stack.hash(&mut hasher);
+        }
+        hasher.finish()
+    };
+
+    let mut position = game.clone();
+    let mut path_positions = HashMap::from([(exact_key(&position), 0)]);
+    for (i, solution_move) in solution.iter().enumerate() {
+        position.execute_move(solution_move.from, solution_move.to, solution_move.count);
+        path_positions.insert(exact_key(&position), i + 1);
+    }
+
+    let mut position = game.clone();
+    let mut shortened = Vec::new();
+    let mut i = 0;
+    while i < solution.len() {
+        // Of all the moves from this position, take the one that lands furthest along the solution
+        let mut best = (i + 1, solution[i]);
+        for from in FOUNDATIONS..FIELD_SIZE {
+            for to in 0..FIELD_SIZE {
+                for count in 1..=position.field[from].len() {
+                    if position.move_is_valid(from, to, count) {
+                        position.execute_move(from, to, count);
+                        if let Some(&j) = path_positions.get(&exact_key(&position)) {
+                            if j > best.0 {
+                                best = (j, Move{from, to, count});
+                            }
+                        }
+                        position.execute_move(to, from, count);
+                    }
+                }
+            }
+        }
+        let (next_i, next_move) = best;
+        position.execute_move(next_move.from, next_move.to, next_move.count);
+        shortened.push(next_move);
+        i = next_i;
+    }
+    shortened
+}
+
+/// Scores how close a position looks to being won. Higher is better.
+fn score_position(game: &Game) -> i32 {
+    let free_cells = &game.field[FOUNDATIONS..FOUNDATIONS + FREE_CELLS];
+    let tableau = &game.field[FOUNDATIONS + FREE_CELLS..];
+    let mut score = 0;
+
+    // Cards on the foundations are progress, free cells and empty columns are room to move
+    score += 10 * game.field[..FOUNDATIONS].iter().map(Vec::len).sum::<usize>();
+    score += 3 * free_cells.iter().filter(|stack| stack.is_empty()).count();
+    score += 6 * tableau.iter().filter(|stack| stack.is_empty()).count();
+
+    // Cards that are buried above the next cards the foundations need are in the way
+    let mut penalty = 0;
+    for stack in tableau {
+        for (depth, card) in stack.iter().enumerate() {
+            let rank = usize::from(card.rank.value());
+            let next_needed = game.field[card.suit.index()].len() + 1;
+            if rank <= next_needed + 1 {
+                penalty += (stack.len() - depth - 1) * (RANKS + 2 - rank);
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    let score = score as i32 - penalty as i32;
+    score
+}
+
+/// Lists the moves worth trying from a position, most promising first.
+fn candidate_moves(game: &Game) -> Vec<Move> {
+    let mut moves = valid_moves(game);
+    if moves.len() > 1 {
+        let mut position = game.clone();
+        let mut scored_moves: Vec<(i32, Move)> = moves.into_iter().map(|candidate| {
+            position.execute_move(candidate.from, candidate.to, candidate.count);
+            let score = score_position(&position);
+            position.execute_move(candidate.to, candidate.from, candidate.count);
+            (score, candidate)
+        }).collect();
+        scored_moves.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        moves = scored_moves.into_iter().map(|(_, candidate)| candidate).collect();
+    }
+    moves
+}
+
+/// Lists the valid moves from a position that could lead somewhere new.
+fn valid_moves(game: &Game) -> Vec<Move> {
+    let free_cells = FOUNDATIONS..FOUNDATIONS + FREE_CELLS;
+    let tableau = FOUNDATIONS + FREE_CELLS..FIELD_SIZE;
+    let mut moves = Vec::new();
+
+    // Moves to the foundations. A safe one can never hurt, so it is the only move worth trying.
+    for from in FOUNDATIONS..FIELD_SIZE {
+        if let Some(&card) = game.field[from].last() {
+            let to = card.suit.index();
+            if game.move_is_valid(from, to, 1) {
+                if game.is_safe_to_stack(card) {
+                    return vec![Move{from, to, count: 1}];
+                }
+                moves.push(Move{from, to, count: 1});
+            }
+        }
+    }
+
+    // Moves onto the cards of the tableau
+    for to in tableau.clone().filter(|&to| !game.field[to].is_empty()) {
+        for from in FOUNDATIONS..FIELD_SIZE {
+            if let Some(count) = game.sequence_move_size(from, to) {
+                moves.push(Move{from, to, count});
+            }
+        }
+    }
+
+    // Moves into an empty tableau column. All empty columns are alike, so only the first one is tried.
+    if let Some(to) = tableau.clone().find(|&to| game.field[to].is_empty()) {
+        for from in FOUNDATIONS..FIELD_SIZE {
+            let longest = game.ordered_sequence_length(from).min(game.max_sequence_move_size(to));
+            // Moving a whole column into another empty column changes nothing
+            let longest = if tableau.contains(&from) {longest.min(game.field[from].len().saturating_sub(1))} else {longest};
+            for count in (1..=longest).rev() {
+                if game.move_is_valid(from, to, count) {
+                    moves.push(Move{from, to, count});
+                }
+            }
+        }
+    }
+
+    // Moves into a free cell. All empty free cells are alike, so only the first one is tried.
+    if let Some(to) = free_cells.clone().find(|&to| game.field[to].is_empty()) {
+        for from in tableau {
+            if game.move_is_valid(from, to, 1) {
+                moves.push(Move{from, to, count: 1});
+            }
+        }
+    }
+
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{atomic::AtomicBool, Arc}, time::Duration};
+
+    use super::{solve, Solution, SolverLimits};
+    use crate::game::Game;
+
+    #[test]
+    fn solution_replays_to_a_win() {
+        let mut game = Game::from_deal_number(1);
+        let Solution::Solvable(moves) = solve(&game, &SolverLimits::default()) else {panic!("deal #1 is solvable")};
+        for solution_move in moves {
+            game.apply_move(solution_move).unwrap();
+        }
+        assert!(game.is_won());
+    }
+
+    #[test]
+    fn impossible_deal_is_unsolvable() {
+        // Deal #11982 is the one Microsoft deal that cannot be won
+        assert!(matches!(solve(&Game::from_deal_number(11982), &SolverLimits::default()), Solution::Unsolvable));
+    }
+
+    #[test]
+    fn limits_stop_the_search() {
+        let game = Game::from_deal_number(11982);
+        let node_limit = SolverLimits {max_nodes: Some(100), ..SolverLimits::default()};
+        assert!(matches!(solve(&game, &node_limit), Solution::Unknown));
+        let time_limit = SolverLimits {time_budget: Some(Duration::ZERO), ..SolverLimits::default()};
+        assert!(matches!(solve(&game, &time_limit), Solution::Unknown));
+        let cancelled = SolverLimits {cancel_flag: Some(Arc::new(AtomicBool::new(true))), ..SolverLimits::default()};
+        assert!(matches!(solve(&game, &cancelled), Solution::Unknown));
+    }
+}
@@ -0,0 +1,370 @@
+//! Keeping statistics of the games played, across launches
+
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}, num::ParseIntError, time::Duration};
+
+use super::{save::{self, LoadError}, Deal, Game};
+
+/// The first line of every statistics file.
+const STATS_FILE_HEADER: &str = "rusty-freecell stats";
+/// The version of the statistics file format written and read by this version of the game.
+const STATS_FORMAT_VERSION: u32 = 1;
+/// The name of the statistics file inside the game's data directory.
+const STATS_FILE_NAME: &str = "stats.txt";
+
+/// The results of the games played on one deal.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct DealStats {
+    /// The number of games on the deal that were won.
+    pub won: u32,
+
+    /// The number of games on the deal that were abandoned for a new game before they were won.
+    pub abandoned: u32,
+
+    /// The fewest moves the deal was won in, if it was ever won.
+    pub fewest_moves: Option<u32>,
+
+    /// The shortest time the deal was won in, if it was ever won with the clock running.
+    pub best_time: Option<Duration>,
+
+    /// The hints asked for in all the finished games on the deal added together.
+    pub hints_used: u32,
+}
+
+impl DealStats {
+    /// Returns the number of games on the deal that were finished, by winning or abandoning them.
+    #[must_use]
+    pub fn played(&self) -> u32 {
+        self.won + self.abandoned
+    }
+}
+
+/// The results of all the games played, overall and per deal.
+/// A game only counts once it is finished, by winning it or by abandoning it for a new game.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Stats {
+    /// The number of games that were won.
+    pub won: u32,
+
+    /// The number of games that were abandoned for a new game before they were won, which count as losses.
+    pub abandoned: u32,
+
+    /// The number of games won in a row since the last loss.
+    pub current_streak: u32,
+
+    /// The most games ever won in a row.
+    pub best_streak: u32,
+
+    /// The moves of all the games that were won added together, to work out the average.
+    pub won_moves: u64,
+
+    /// The play times of all the games that were won added together, to work out the average.
+    pub won_time: Duration,
+
+    /// The shortest time any game was won in, if one was ever won with the clock running.
+    pub best_time: Option<Duration>,
+
+    /// The hints asked for in all the finished games added together.
+    pub hints_used: u64,
+
+    /// The results per deal, for the games that were dealt from a reproducible deal.
+    pub deals: BTreeMap<Deal, DealStats>,
+}
+
+/// Returns the path of the statistics file inside the game's data directory, see `save::data_dir_path`.
+///
+/// # Returns
+///
+/// The path of the statistics file, or `None` if neither `$XDG_DATA_HOME` nor `$HOME` is set.
+#[must_use]
+pub fn stats_file_path() -> Option<PathBuf> {
+    Some(save::data_dir_path()?.join(STATS_FILE_NAME))
+}
+
+impl Stats {
+    /// Returns the number of games that were finished, by winning or abandoning them.
+    #[must_use]
+    pub fn played(&self) -> u32 {
+        self.won + self.abandoned
+    }
+
+    /// Returns the average number of moves of the games that were won, rounded down, or `None` if no game was won.
+    #[must_use]
+    pub fn average_moves(&self) -> Option<u64> {
+        self.won_moves.checked_div(u64::from(self.won))
+    }
+
+    /// Returns the average play time of the games that were won, or `None` if no game was won.
+    #[must_use]
+    pub fn average_time(&self) -> Option<Duration> {
+        self.won_time.checked_div(self.won)
+    }
+
+    /// Records a game that was won in a number of moves and a play time, with the hints asked for in it, extending the win streak.
+    /// A game won without the clock ever running, such as one replayed to the end at launch, sets no best time.
+    pub fn record_win(&mut self, deal_opt: Option<Deal>, moves: u32, time: Duration, hints_used: u32) {
+        self.won += 1;
+        self.hints_used += u64::from(hints_used);
+        self.current_streak += 1;
+        self.best_streak = self.best_streak.max(self.current_streak);
+        self.won_moves += u64::from(moves);
+        self.won_time += time;
+        let best_time = |best_time_opt: Option<Duration>| {
+            if time.is_zero() {best_time_opt} else {Some(best_time_opt.map_or(time, |best_time| best_time.min(time)))}
+        };
+        self.best_time = best_time(self.best_time);
+        if let Some(deal) = deal_opt {
+            let deal_stats = self.deals.entry(deal).or_default();
+            deal_stats.won += 1;
+            deal_stats.fewest_moves = Some(deal_stats.fewest_moves.map_or(moves, |fewest_moves| fewest_moves.min(moves)));
+            deal_stats.best_time = best_time(deal_stats.best_time);
+            deal_stats.hints_used += hints_used;
+        }
+    }
+
+    /// Records a game that was abandoned before it was won, with the hints asked for in it, ending the win streak.
+    pub fn record_loss(&mut self, deal_opt: Option<Deal>, hints_used: u32) {
+        self.abandoned += 1;
+        self.current_streak = 0;
+        self.hints_used += u64::from(hints_used);
+        if let Some(deal) = deal_opt {
+            let deal_stats = self.deals.entry(deal).or_default();
+            deal_stats.abandoned += 1;
+            deal_stats.hints_used += hints_used;
+        }
+    }
+
+    /// Records the result of a finished game: a win if it is won, otherwise a loss,
+    /// such as when it is abandoned for a new game or the player declines to resume it. A game without a single move is not counted.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the game was counted, otherwise `false`.
+    pub fn record_game(&mut self, game: &Game) -> bool {
+        if game.move_count() == 0 {return false;}
+        if game.is_won() {
+            self.record_win(game.deal(), game.move_count(), game.elapsed_time(), game.hints_used());
+        } else {
+            self.record_loss(game.deal(), game.hints_used());
+        }
+        true
+    }
+
+    /// Saves the statistics to a file, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file or its directory cannot be written.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), io::Error> {
+        save::write_file_atomically(path, &self.to_stats_string())
+    }
+
+    /// Loads statistics previously written by `save_to_file`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LoadError` if the file cannot be read or is malformed.
+    pub fn load_from_file(path: &Path) -> Result<Stats, LoadError> {
+        Stats::from_stats_string(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the statistics in the statistics file format.
+    fn to_stats_string(&self) -> String {
+        let mut lines = vec![
+            STATS_FILE_HEADER.to_owned(),
+            format!("version {STATS_FORMAT_VERSION}"),
+            format!("won {}", self.won),
+            format!("abandoned {}", self.abandoned),
+            format!("streak {} {}", self.current_streak, self.best_streak),
+            format!("won-moves {}", self.won_moves),
+            format!("won-time {}", self.won_time.as_millis()),
+            format!("best-time {}", time_to_string(self.best_time)),
+            format!("hints {}", self.hints_used)
+        ];
+        for (deal, deal_stats) in &self.deals {
+            let deal = match deal {
+                Deal::Numbered(deal_number) => format!("number {deal_number}"),
+                Deal::Seeded(seed) => format!("seed {seed}")
+            };
+            let fewest_moves = deal_stats.fewest_moves.map_or("-".to_owned(), |fewest_moves| fewest_moves.to_string());
+            lines.push(format!("deal {deal} {} {} {fewest_moves} {} {}",
+                deal_stats.won, deal_stats.abandoned, time_to_string(deal_stats.best_time), deal_stats.hints_used));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads statistics written in the statistics file format.
+    fn from_stats_string(contents: &str) -> Result<Stats, LoadError> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(STATS_FILE_HEADER) {
+            return Err(LoadError::Malformed("not a rusty-freecell statistics file".to_owned()));
+        }
+        let version = lines.next()
+            .and_then(|line| line.strip_prefix("version "))
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or(LoadError::Malformed("missing format version".to_owned()))?;
+        if version != STATS_FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion(version, STATS_FORMAT_VERSION));
+        }
+
+        let mut stats = Stats::default();
+        for line in lines {
+            let malformed = || LoadError::Malformed(format!("cannot read line \"{line}\""));
+            let parse_number = |value: &str| value.parse().map_err(|_| malformed());
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "won" => {
+                    stats.won = parse_number(value)?;
+                },
+                "abandoned" => {
+                    stats.abandoned = parse_number(value)?;
+                },
+                "streak" => {
+                    let (current_streak, best_streak) = value.split_once(' ').ok_or_else(malformed)?;
+                    stats.current_streak = parse_number(current_streak)?;
+                    stats.best_streak = parse_number(best_streak)?;
+                },
+                "won-moves" => {
+                    stats.won_moves = value.parse().map_err(|_| malformed())?;
+                },
+                "won-time" => {
+                    stats.won_time = Duration::from_millis(value.parse().map_err(|_| malformed())?);
+                },
+                "best-time" => {
+                    stats.best_time = time_from_string(value).map_err(|_| malformed())?;
+                },
+                "hints" => {
+                    stats.hints_used = value.parse().map_err(|_| malformed())?;
+                },
+                "deal" => {
+                    let [kind, id, won, abandoned, fewest_moves, best_time, hints_used] = value.split(' ').collect::<Vec<&str>>()[..] else {
+                        return Err(malformed());
+                    };
+                    let best_time = time_from_string(best_time).map_err(|_| malformed())?;
+                    let deal = match kind {
+                        "number" => Deal::Numbered(parse_number(id)?),
+                        "seed" => Deal::Seeded(id.parse().map_err(|_| malformed())?),
+                        _ => return Err(malformed())
+                    };
+                    let fewest_moves = if fewest_moves == "-" {None} else {Some(parse_number(fewest_moves)?)};
+                    stats.deals.insert(deal, DealStats {
+                        won: parse_number(won)?,
+                        abandoned: parse_number(abandoned)?,
+                        fewest_moves,
+                        best_time,
+                        hints_used: parse_number(hints_used)?
+                    });
+                },
+                _ => {
+                    return Err(malformed());
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Writes an optional time as whole milliseconds, or "-" if there is none.
+fn time_to_string(time_opt: Option<Duration>) -> String {
+    time_opt.map_or("-".to_owned(), |time| time.as_millis().to_string())
+}
+
+/// Reads an optional time written by `time_to_string`.
+fn time_from_string(string: &str) -> Result<Option<Duration>, ParseIntError> {
+    if string == "-" {return Ok(None);}
+    string.parse().map(|millis| Some(Duration::from_millis(millis)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{DealStats, Stats};
+    use crate::game::{Deal, Game};
+
+    #[test]
+    fn hints_are_counted_overall_and_per_deal() {
+        let mut stats = Stats::default();
+        stats.record_win(Some(Deal::Numbered(1)), 90, Duration::from_secs(100), 2);
+        stats.record_loss(Some(Deal::Numbered(1)), 3);
+        stats.record_loss(None, 4);
+        assert_eq!(stats.hints_used, 9);
+        assert_eq!(stats.deals[&Deal::Numbered(1)].hints_used, 5);
+    }
+
+    #[test]
+    fn statistics_load_the_same() {
+        let mut stats = Stats::default();
+        stats.record_win(Some(Deal::Numbered(1)), 90, Duration::from_millis(100_250), 2);
+        stats.record_loss(Some(Deal::Seeded(u64::MAX)), 0);
+        stats.record_win(Some(Deal::Seeded(u64::MAX)), 120, Duration::ZERO, 0);
+        stats.record_loss(None, 1);
+        let loaded = Stats::from_stats_string(&stats.to_stats_string()).unwrap();
+        assert_eq!(loaded, stats);
+    }
+
+    #[test]
+    fn streaks_count_the_wins_since_the_last_loss() {
+        let mut stats = Stats::default();
+        for won in [true, true, false, true, true, true, false, true] {
+            if won {
+                stats.record_win(None, 100, Duration::from_secs(60), 0);
+            } else {
+                stats.record_loss(None, 0);
+            }
+        }
+        assert_eq!((stats.current_streak, stats.best_streak), (1, 3));
+        assert_eq!((stats.won, stats.abandoned, stats.played()), (6, 2, 8));
+    }
+
+    #[test]
+    fn version_1_files_load() {
+        let stats = Stats::from_stats_string("\
+            rusty-freecell stats\n\
+            version 1\n\
+            won 2\n\
+            abandoned 1\n\
+            streak 0 2\n\
+            won-moves 190\n\
+            won-time 300000\n\
+            best-time 120000\n\
+            hints 3\n\
+            deal number 1 2 0 90 120000 3\n\
+            deal seed 42 0 1 - - 0\n").unwrap();
+        assert_eq!((stats.won, stats.abandoned, stats.current_streak, stats.best_streak), (2, 1, 0, 2));
+        assert_eq!(stats.average_moves(), Some(95));
+        assert_eq!(stats.average_time(), Some(Duration::from_secs(150)));
+        assert_eq!(stats.best_time, Some(Duration::from_secs(120)));
+        assert_eq!(stats.deals[&Deal::Numbered(1)], DealStats {
+            won: 2, abandoned: 0, fewest_moves: Some(90), best_time: Some(Duration::from_secs(120)), hints_used: 3
+        });
+        assert_eq!(stats.deals[&Deal::Seeded(42)], DealStats {abandoned: 1, ..DealStats::default()});
+    }
+
+    #[test]
+    fn unfinished_game_counts_as_a_loss_once_it_has_moves() {
+        // Such as a saved game the player declines to resume
+        let mut stats = Stats::default();
+        let mut game = Game::from_deal_number(1);
+        assert!(!stats.record_game(&game));
+        assert_eq!(stats, Stats::default());
+
+        game.replay("1a").unwrap();
+        assert!(stats.record_game(&game));
+        assert_eq!((stats.won, stats.abandoned), (0, 1));
+        assert_eq!(stats.deals[&Deal::Numbered(1)].abandoned, 1);
+    }
+
+    #[test]
+    fn best_time_leaves_out_wins_without_a_time() {
+        let deal = Some(Deal::Numbered(1));
+        let mut stats = Stats::default();
+        stats.record_win(deal, 90, Duration::ZERO, 0);
+        assert_eq!((stats.best_time, stats.deals[&Deal::Numbered(1)].best_time), (None, None));
+        for seconds in [100, 50, 0, 80] {
+            stats.record_win(deal, 90, Duration::from_secs(seconds), 0);
+        }
+        let best_time = Some(Duration::from_secs(50));
+        assert_eq!((stats.best_time, stats.deals[&Deal::Numbered(1)].best_time), (best_time, best_time));
+        assert_eq!(stats.average_time(), Some(Duration::from_secs(46)));
+    }
+}
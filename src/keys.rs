@@ -0,0 +1,665 @@
+//! Key bindings: which key chords trigger which actions, and reading them from a config file
+//!
+//! The config file is `$XDG_CONFIG_HOME/rusty-freecell/keys.toml`, or `~/.config/rusty-freecell/keys.toml` if that is not set.
+//! It picks a preset to start from and rebinds actions in a `[keys]` table, each to a key chord or a list of them.
+//! An empty list unbinds the action. For example:
+//!
+//! ```toml
+//! preset = "vim"
+//!
+//! [keys]
+//! undo = ["u", "z"]
+//! hint = "shift-h"
+//! auto-play = []
+//! ```
+//!
+//! Only this much of TOML is read: the `preset` setting, the `[keys]` table, strings, single-line arrays of strings
+//! and `#` comments.
+
+use std::{fmt, fs, io, path::{Path, PathBuf}, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::game::{FREE_CELLS, TABLEAU_SIZE};
+
+/// The name of the key bindings file inside the game's config directory.
+const CONFIG_FILE_NAME: &str = "keys.toml";
+/// The name of the table of the config file that rebinds actions.
+const KEYS_TABLE: &str = "keys";
+/// The name of the setting of the config file that picks the preset.
+const PRESET_SETTING: &str = "preset";
+
+/// Keys that are written by name rather than as the character they type.
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("space", KeyCode::Char(' ')), ("left", KeyCode::Left), ("right", KeyCode::Right), ("up", KeyCode::Up),
+    ("down", KeyCode::Down), ("enter", KeyCode::Enter), ("esc", KeyCode::Esc), ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace), ("delete", KeyCode::Delete), ("insert", KeyCode::Insert), ("home", KeyCode::Home),
+    ("end", KeyCode::End), ("pageup", KeyCode::PageUp), ("pagedown", KeyCode::PageDown)
+];
+
+/// Modifiers as they are written before a key, such as the "ctrl" in "ctrl-n".
+const MODIFIER_NAMES: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "ctrl"), (KeyModifiers::ALT, "alt"), (KeyModifiers::SHIFT, "shift")
+];
+
+/// An action the player can bind keys to.
+/// Not to be confused with `game::Action`, which groups the moves that are undone together.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Command {
+    /// Move the cursor one stack to the left.
+    CursorLeft,
+    /// Move the cursor one stack to the right.
+    CursorRight,
+    /// Move the cursor up, first taking in more cards of the sequence at the top of a tableau column.
+    CursorUp,
+    /// Move the cursor down, first letting go of cards of the sequence at the top of a tableau column.
+    CursorDown,
+    /// Select the highlighted card, or move the selected cards to the highlighted stack.
+    Select,
+    /// Select a tableau column or move the selected cards onto it, counted from 0 for the leftmost column.
+    Column(usize),
+    /// Select a free cell or move the selected card into it, counted from 0 for the leftmost free cell.
+    FreeCell(usize),
+    /// Move the selected card, or else the highlighted one, to its foundation.
+    Foundation,
+    /// Send the selected cards, or else the highlighted ones, to the best place for them.
+    Send,
+    /// Undo the last action.
+    Undo,
+    /// Redo the last undone action.
+    Redo,
+    /// Move every card that can go to the foundations there.
+    QuickStack,
+    /// Show a hint.
+    Hint,
+    /// Toggle auto-play.
+    AutoPlay,
+    /// Toggle the high contrast display mode.
+    HighContrast,
+    /// Start a new game.
+    NewGame,
+    /// Restart the current deal.
+    Restart,
+    /// Export the moves of the current game.
+    ExportMoves,
+    /// Export the current position.
+    ExportBoard,
+    /// Show the help screen listing the key bindings.
+    Help,
+    /// Show the statistics screen.
+    Stats,
+    /// Pause the game, stopping the clock and hiding the board.
+    Pause,
+    /// Quit the game.
+    Quit,
+}
+
+impl Command {
+    /// All actions, in the order the help screen lists them.
+    pub const ALL: [Command; 21 + TABLEAU_SIZE + FREE_CELLS] = [
+        Command::CursorLeft, Command::CursorRight, Command::CursorUp, Command::CursorDown, Command::Select,
+        Command::Column(0), Command::Column(1), Command::Column(2), Command::Column(3),
+        Command::Column(4), Command::Column(5), Command::Column(6), Command::Column(7),
+        Command::FreeCell(0), Command::FreeCell(1), Command::FreeCell(2), Command::FreeCell(3),
+        Command::Foundation, Command::Send, Command::Undo, Command::Redo, Command::QuickStack, Command::Hint, Command::AutoPlay,
+        Command::HighContrast, Command::NewGame, Command::Restart, Command::ExportMoves, Command::ExportBoard,
+        Command::Pause, Command::Help, Command::Stats, Command::Quit
+    ];
+
+    /// Returns what the action does, as listed on the help screen.
+    /// The actions that move the cursor share one description, and so do the actions for each tableau column,
+    /// the actions for each free cell and the two export actions.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Command::CursorLeft | Command::CursorRight | Command::CursorUp | Command::CursorDown => "Move cursor",
+            Command::Select => "Select/move card",
+            Command::Column(_) => "Select/move to a column",
+            Command::FreeCell(_) => "Select/move to a free cell",
+            Command::Foundation => "Move card to foundation",
+            Command::Send => "Send card to best place",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::QuickStack => "Quick stack to foundations",
+            Command::Hint => "Hint",
+            Command::AutoPlay => "Toggle auto-play",
+            Command::HighContrast => "Toggle high contrast",
+            Command::NewGame => "New game",
+            Command::Restart => "Restart the deal",
+            Command::ExportMoves | Command::ExportBoard => "Export moves/board",
+            Command::Pause => "Pause",
+            Command::Help => "Show this help",
+            Command::Stats => "Show statistics",
+            Command::Quit => "Quit"
+        }
+    }
+
+    /// Returns the action with the given name in the config file, such as "undo" or "column-1".
+    fn from_name(name: &str) -> Option<Command> {
+        Command::ALL.into_iter().find(|command| command.to_string() == name)
+    }
+}
+
+/// Writes the name of the action in the config file, such as "undo" or "column-1".
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Command::Column(column) => return write!(f, "column-{}", column + 1),
+            Command::FreeCell(free_cell) => return write!(f, "free-cell-{}", free_cell + 1),
+            Command::CursorLeft => "cursor-left",
+            Command::CursorRight => "cursor-right",
+            Command::CursorUp => "cursor-up",
+            Command::CursorDown => "cursor-down",
+            Command::Select => "select",
+            Command::Foundation => "foundation",
+            Command::Send => "send",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::QuickStack => "quick-stack",
+            Command::Hint => "hint",
+            Command::AutoPlay => "auto-play",
+            Command::HighContrast => "high-contrast",
+            Command::NewGame => "new-game",
+            Command::Restart => "restart",
+            Command::ExportMoves => "export-moves",
+            Command::ExportBoard => "export-board",
+            Command::Pause => "pause",
+            Command::Help => "help",
+            Command::Stats => "stats",
+            Command::Quit => "quit"
+        };
+        f.write_str(name)
+    }
+}
+
+/// A key together with the modifiers held down with it, such as ctrl-n.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct KeyChord {
+    /// The key that is pressed.
+    code: KeyCode,
+
+    /// The modifiers held down with the key. Never includes shift for a character key, whose character already shows it.
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Creates a key chord from a key and the modifiers held down with it.
+    #[must_use]
+    pub fn new(code: KeyCode, mut modifiers: KeyModifiers) -> KeyChord {
+        // Terminals disagree on whether they report shift along with the character it typed
+        if matches!(code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        KeyChord{code, modifiers}
+    }
+
+    /// Creates a key chord from a key pressed without modifiers.
+    const fn key(code: KeyCode) -> KeyChord {
+        KeyChord{code, modifiers: KeyModifiers::NONE}
+    }
+
+    /// Creates a key chord from a character typed without modifiers. An uppercase letter is typed with shift.
+    const fn char(c: char) -> KeyChord {
+        KeyChord::key(KeyCode::Char(c))
+    }
+
+    /// Creates a key chord from a character typed with ctrl held down.
+    const fn ctrl(c: char) -> KeyChord {
+        KeyChord{code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL}
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key_event: KeyEvent) -> Self {
+        KeyChord::new(key_event.code, key_event.modifiers)
+    }
+}
+
+/// Writes the key chord the way the config file does, such as "ctrl-n", "shift-z", "left" or "?".
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}-")?;
+            }
+        }
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, code)| *code == self.code) {
+            return f.write_str(name);
+        }
+        match self.code {
+            KeyCode::Char(c) if c.is_uppercase() => write!(f, "shift-{}", c.to_lowercase()),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(number) => write!(f, "f{number}"),
+            code => write!(f, "{}", format!("{code:?}").to_lowercase())
+        }
+    }
+}
+
+/// Reads a key chord from any modifiers, each followed by a `-`, and then a key.
+/// The modifiers are ctrl, alt and shift. The key is a single character, f1 to f12, or one of the names space, left, right,
+/// up, down, enter, esc, tab, backspace, delete, insert, home, end, pageup and pagedown.
+/// Letters are case sensitive, so "Z" is the same key chord as "shift-z".
+impl FromStr for KeyChord {
+    type Err = KeyBindingsError;
+
+    fn from_str(name: &str) -> Result<KeyChord, KeyBindingsError> {
+        let invalid = || KeyBindingsError::InvalidKey(name.to_owned());
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key_name = name;
+        // The key itself may be a '-', so a '-' at the end never follows a modifier
+        while let Some((prefix, rest)) = key_name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            let (modifier, _) = MODIFIER_NAMES.into_iter()
+                .find(|(_, modifier_name)| prefix.eq_ignore_ascii_case(modifier_name))
+                .ok_or_else(invalid)?;
+            modifiers |= modifier;
+            key_name = rest;
+        }
+
+        let mut chars = key_name.chars();
+        let code = if let (Some(c), None) = (chars.next(), chars.next()) {
+            KeyCode::Char(c)
+        } else if let Some((_, code)) = NAMED_KEYS.iter().find(|(named_key, _)| key_name.eq_ignore_ascii_case(named_key)) {
+            *code
+        } else {
+            let number = key_name.strip_prefix(['f', 'F']).and_then(|number| number.parse::<u8>().ok());
+            KeyCode::F(number.filter(|number| (1..=12).contains(number)).ok_or_else(invalid)?)
+        };
+
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                // Shift only changes the character typed by a letter, other characters are written as they are typed
+                if !c.is_ascii_alphabetic() {return Err(invalid())}
+                Ok(KeyChord::new(KeyCode::Char(c.to_ascii_uppercase()), modifiers))
+            },
+            _ => Ok(KeyChord::new(code, modifiers))
+        }
+    }
+}
+
+/// A set of key bindings to start from, before the config file rebinds actions.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Preset {
+    /// The key bindings described in the README.
+    #[default]
+    Standard,
+    /// Vim-style key bindings: `h`, `j`, `k` and `l` move the cursor, `u` undoes and ctrl-r redoes.
+    Vim,
+}
+
+impl Preset {
+    /// All presets.
+    pub const ALL: [Preset; 2] = [Preset::Standard, Preset::Vim];
+
+    /// Returns the name of the preset in the config file.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Standard => "default",
+            Preset::Vim => "vim"
+        }
+    }
+}
+
+/// The default key bindings.
+const STANDARD_KEYS: [(Command, &[KeyChord]); 21 + TABLEAU_SIZE + FREE_CELLS] = [
+    (Command::CursorLeft, &[KeyChord::key(KeyCode::Left), KeyChord::char('a')]),
+    (Command::CursorRight, &[KeyChord::key(KeyCode::Right), KeyChord::char('d')]),
+    (Command::CursorUp, &[KeyChord::key(KeyCode::Up)]),
+    (Command::CursorDown, &[KeyChord::key(KeyCode::Down)]),
+    (Command::Select, &[KeyChord::char(' '), KeyChord::key(KeyCode::Enter)]),
+    (Command::Column(0), &[KeyChord::char('1')]),
+    (Command::Column(1), &[KeyChord::char('2')]),
+    (Command::Column(2), &[KeyChord::char('3')]),
+    (Command::Column(3), &[KeyChord::char('4')]),
+    (Command::Column(4), &[KeyChord::char('5')]),
+    (Command::Column(5), &[KeyChord::char('6')]),
+    (Command::Column(6), &[KeyChord::char('7')]),
+    (Command::Column(7), &[KeyChord::char('8')]),
+    (Command::FreeCell(0), &[KeyChord::char('q')]),
+    (Command::FreeCell(1), &[KeyChord::char('w')]),
+    (Command::FreeCell(2), &[KeyChord::char('e')]),
+    (Command::FreeCell(3), &[KeyChord::char('r')]),
+    (Command::Foundation, &[KeyChord::char('t')]),
+    (Command::Send, &[KeyChord::char('s')]),
+    (Command::Undo, &[KeyChord::char('z')]),
+    (Command::Redo, &[KeyChord::char('Z'), KeyChord::ctrl('y')]),
+    (Command::QuickStack, &[KeyChord::char('f')]),
+    (Command::Hint, &[KeyChord::char('?')]),
+    (Command::AutoPlay, &[KeyChord::char('o')]),
+    (Command::HighContrast, &[KeyChord::char('h')]),
+    (Command::NewGame, &[KeyChord::ctrl('n')]),
+    (Command::Restart, &[KeyChord::ctrl('r')]),
+    (Command::ExportMoves, &[KeyChord::ctrl('e')]),
+    (Command::ExportBoard, &[KeyChord::ctrl('b')]),
+    (Command::Pause, &[KeyChord::char('p')]),
+    (Command::Help, &[KeyChord::key(KeyCode::F(1))]),
+    (Command::Stats, &[KeyChord::key(KeyCode::F(2))]),
+    (Command::Quit, &[KeyChord::ctrl('q')])
+];
+
+/// The vim-style key bindings, as changes to the default ones. The actions they push off `h` and ctrl-r move to `c` and `R`.
+const VIM_KEYS: [(Command, &[KeyChord]); 8] = [
+    (Command::CursorLeft, &[KeyChord::char('h'), KeyChord::key(KeyCode::Left)]),
+    (Command::CursorRight, &[KeyChord::char('l'), KeyChord::key(KeyCode::Right)]),
+    (Command::CursorUp, &[KeyChord::char('k'), KeyChord::key(KeyCode::Up)]),
+    (Command::CursorDown, &[KeyChord::char('j'), KeyChord::key(KeyCode::Down)]),
+    (Command::Undo, &[KeyChord::char('u')]),
+    (Command::Redo, &[KeyChord::ctrl('r')]),
+    (Command::HighContrast, &[KeyChord::char('c')]),
+    (Command::Restart, &[KeyChord::char('R')])
+];
+
+/// An error that occurred while reading key bindings from the config file.
+#[derive(Debug)]
+pub enum KeyBindingsError {
+    /// The config file could not be read.
+    Io(io::Error),
+    /// A line of the config file could not be understood.
+    Malformed(String),
+    /// The config file rebinds an action that does not exist. Holds the name as written.
+    UnknownAction(String),
+    /// The config file picks a preset that does not exist. Holds the name as written.
+    UnknownPreset(String),
+    /// The config file binds an action to something that is not a key chord. Holds the key chord as written.
+    InvalidKey(String),
+    /// A key chord is bound to two actions.
+    Conflict(KeyChord, Command, Command),
+}
+
+impl fmt::Display for KeyBindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyBindingsError::Io(error) => write!(f, "{error}"),
+            KeyBindingsError::Malformed(problem) => write!(f, "malformed key bindings: {problem}"),
+            KeyBindingsError::UnknownAction(name) => {
+                let names: Vec<String> = Command::ALL.iter().map(ToString::to_string).collect();
+                write!(f, "unknown action \"{name}\", expected one of {}", names.join(", "))
+            },
+            KeyBindingsError::UnknownPreset(name) => {
+                let names: Vec<String> = Preset::ALL.iter().map(|preset| format!("\"{}\"", preset.name())).collect();
+                write!(f, "unknown preset \"{name}\", expected one of {}", names.join(", "))
+            },
+            KeyBindingsError::InvalidKey(name) => write!(f, "\"{name}\" is not a key, such as \"z\", \"shift-z\", \"ctrl-n\", \"left\" or \"f1\""),
+            KeyBindingsError::Conflict(chord, first, second) => write!(f, "\"{chord}\" is bound to both {first} and {second}")
+        }
+    }
+}
+
+impl std::error::Error for KeyBindingsError {}
+
+impl From<io::Error> for KeyBindingsError {
+    fn from(error: io::Error) -> Self {
+        KeyBindingsError::Io(error)
+    }
+}
+
+/// Returns the path of the key bindings file, `$XDG_CONFIG_HOME/rusty-freecell/keys.toml`,
+/// or `~/.config/rusty-freecell/keys.toml` if that is not set.
+///
+/// # Returns
+///
+/// The path of the key bindings file, or `None` if neither `$XDG_CONFIG_HOME` nor `$HOME` is set.
+#[must_use]
+pub fn config_file_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("rusty-freecell").join(CONFIG_FILE_NAME))
+}
+
+/// The key chords bound to each action.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyBindings {
+    /// Every action, in the order of `Command::ALL`, with the key chords bound to it.
+    keys: Vec<(Command, Vec<KeyChord>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::from_preset(Preset::default())
+    }
+}
+
+impl KeyBindings {
+    /// Creates the key bindings of a preset.
+    #[must_use]
+    pub fn from_preset(preset: Preset) -> KeyBindings {
+        let mut bindings = KeyBindings{keys: STANDARD_KEYS.iter().map(|(command, chords)| (*command, chords.to_vec())).collect()};
+        if preset == Preset::Vim {
+            for (command, chords) in VIM_KEYS {
+                bindings.rebind(command, chords.to_vec());
+            }
+        }
+        bindings
+    }
+
+    /// Reads key bindings from the contents of a config file, see the module documentation.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyBindingsError` if the config is malformed, names an unknown action, preset or key,
+    /// or binds a key chord to two actions.
+    pub fn from_config_str(config: &str) -> Result<KeyBindings, KeyBindingsError> {
+        let mut preset = Preset::default();
+        let mut rebindings = Vec::new();
+        let mut in_keys_table = false;
+        for line in config.lines().map(|line| strip_comment(line).trim()).filter(|line| !line.is_empty()) {
+            let malformed = || KeyBindingsError::Malformed(format!("cannot read line \"{line}\""));
+            if let Some(table) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                if table.trim() != KEYS_TABLE {
+                    return Err(KeyBindingsError::Malformed(format!("unknown table [{table}], expected [{KEYS_TABLE}]")));
+                }
+                in_keys_table = true;
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(malformed)?;
+            let name = name.trim();
+            let values = parse_strings(value.trim()).ok_or_else(malformed)?;
+            if in_keys_table {
+                let command = Command::from_name(name).ok_or_else(|| KeyBindingsError::UnknownAction(name.to_owned()))?;
+                let chords = values.iter().map(|value| value.parse()).collect::<Result<_, _>>()?;
+                rebindings.push((command, chords));
+            } else if name == PRESET_SETTING {
+                let [preset_name] = values.as_slice() else {return Err(malformed())};
+                preset = Preset::ALL.into_iter()
+                    .find(|preset| preset.name() == preset_name)
+                    .ok_or_else(|| KeyBindingsError::UnknownPreset(preset_name.clone()))?;
+            } else {
+                return Err(KeyBindingsError::Malformed(format!("unknown setting \"{name}\", expected \"{PRESET_SETTING}\" or a [{KEYS_TABLE}] table")));
+            }
+        }
+
+        // The preset applies first wherever it is set in the file
+        let mut bindings = KeyBindings::from_preset(preset);
+        for (command, chords) in rebindings {
+            bindings.rebind(command, chords);
+        }
+        bindings.check_conflicts()?;
+        Ok(bindings)
+    }
+
+    /// Loads key bindings from a config file, see `from_config_str`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `KeyBindingsError` if the file cannot be read or its key bindings are invalid.
+    pub fn load_config_file(path: &Path) -> Result<KeyBindings, KeyBindingsError> {
+        KeyBindings::from_config_str(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the action bound to a key chord, if there is one.
+    #[must_use]
+    pub fn command_for(&self, chord: KeyChord) -> Option<Command> {
+        self.keys.iter().find(|(_, chords)| chords.contains(&chord)).map(|(command, _)| *command)
+    }
+
+    /// Returns the key chords bound to an action, the main one first.
+    #[must_use]
+    pub fn keys_for(&self, command: Command) -> &[KeyChord] {
+        self.keys.iter().find(|(bound_command, _)| *bound_command == command).map_or(&[], |(_, chords)| chords)
+    }
+
+    /// Replaces the key chords bound to an action.
+    fn rebind(&mut self, command: Command, chords: Vec<KeyChord>) {
+        if let Some((_, bound_chords)) = self.keys.iter_mut().find(|(bound_command, _)| *bound_command == command) {
+            *bound_chords = chords;
+        }
+    }
+
+    /// Checks that no key chord is bound to two actions.
+    fn check_conflicts(&self) -> Result<(), KeyBindingsError> {
+        for (i, (command, chords)) in self.keys.iter().enumerate() {
+            for (other_command, other_chords) in &self.keys[i + 1..] {
+                if let Some(chord) = chords.iter().find(|chord| other_chords.contains(chord)) {
+                    return Err(KeyBindingsError::Conflict(*chord, *command, *other_command));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cuts a `#` comment off the end of a line of the config file, leaving any `#` inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote_opt = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote_opt {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(quote) if c == quote => quote_opt = None,
+            None if c == '"' || c == '\'' => quote_opt = Some(c),
+            None if c == '#' => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Reads the value of a setting of the config file, either a string or an array of strings.
+///
+/// # Returns
+///
+/// The strings of the value, or `None` if it is neither a string nor an array of strings.
+fn parse_strings(value: &str) -> Option<Vec<String>> {
+    let (mut rest, is_array) = match value.strip_prefix('[') {
+        Some(array) => (array.strip_suffix(']')?.trim_start(), true),
+        None => (value, false)
+    };
+    let mut strings = Vec::new();
+    while !rest.is_empty() {
+        let (string, after_string) = parse_string(rest)?;
+        strings.push(string);
+        rest = after_string.trim_start();
+        if let Some(after_comma) = rest.strip_prefix(',').filter(|_| is_array) {
+            rest = after_comma.trim_start();
+        } else if !rest.is_empty() {
+            return None;
+        }
+    }
+    (is_array || strings.len() == 1).then_some(strings)
+}
+
+/// Reads a string from the start of some text, either a basic string in double quotes, where `\"` and `\\` are escapes,
+/// or a literal string in single quotes.
+///
+/// # Returns
+///
+/// The string and the text after it, or `None` if the text does not start with a string.
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.char_indices();
+    let quote = chars.next().map(|(_, c)| c).filter(|&c| c == '"' || c == '\'')?;
+    let mut string = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if c == quote => return Some((string, &text[i + 1..])),
+            '\\' if quote == '"' => match chars.next()? {
+                (_, escaped @ ('"' | '\\')) => string.push(escaped),
+                _ => return None
+            },
+            _ => string.push(c)
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_strings, strip_comment, Command, KeyBindings, KeyBindingsError, KeyChord, Preset};
+
+    /// Parses a key chord that is known to be valid.
+    fn chord(name: &str) -> KeyChord {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn key_chords_read_back_the_same() {
+        for name in ["z", "shift-z", "ctrl-n", "ctrl-shift-left", "alt-f1", "space", "-", "ctrl--", "?"] {
+            assert_eq!(chord(name).to_string(), name);
+        }
+        assert_eq!(chord("Z"), chord("shift-z"));
+        assert_eq!(chord("CTRL-Enter"), chord("ctrl-enter"));
+        for name in ["", "zz", "hyper-z", "ctrl-", "f0", "f13x"] {
+            assert!(matches!(name.parse::<KeyChord>(), Err(KeyBindingsError::InvalidKey(_))), "{name}");
+        }
+    }
+
+    #[test]
+    fn strings_are_read_with_quotes_escapes_and_comments() {
+        assert_eq!(strip_comment("undo = \"#\" # the hash key"), "undo = \"#\" ");
+        assert_eq!(strip_comment("undo = '\"#' # a quote and a hash"), "undo = '\"#' ");
+        assert_eq!(strip_comment("undo = \"\\\"#\""), "undo = \"\\\"#\"");
+        assert_eq!(parse_strings("\"z\""), Some(vec!["z".to_owned()]));
+        assert_eq!(parse_strings("[ \"\\\\\", '\\', \"\\\"\" ]"), Some(vec!["\\".to_owned(), "\\".to_owned(), "\"".to_owned()]));
+        assert_eq!(parse_strings("[]"), Some(Vec::new()));
+        for value in ["z", "\"z", "\"\\n\"", "\"z\" \"x\"", "\"z\", \"x\"", "[\"z\" \"x\"]", "[\"z\""] {
+            assert_eq!(parse_strings(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn config_rebinds_actions_over_the_preset() {
+        let bindings = KeyBindings::from_config_str("\
+            # Start from the vim keys\n\
+            preset = 'vim'\n\
+            \n\
+            [keys]\n\
+            hint = \"shift-h\" # instead of ?\n\
+            quick-stack = [\"#\", 'ctrl-f']\n\
+            auto-play = []\n").unwrap();
+        assert_eq!(bindings.command_for(chord("u")), Some(Command::Undo));
+        assert_eq!(bindings.keys_for(Command::Hint), [chord("shift-h")]);
+        assert_eq!(bindings.command_for(chord("?")), None);
+        assert_eq!(bindings.keys_for(Command::QuickStack), [chord("#"), chord("ctrl-f")]);
+        assert!(bindings.keys_for(Command::AutoPlay).is_empty());
+        assert_eq!(KeyBindings::from_config_str("").unwrap(), KeyBindings::default());
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let error = |config: &str| KeyBindings::from_config_str(config).unwrap_err();
+        assert!(matches!(error("preset = \"emacs\""), KeyBindingsError::UnknownPreset(name) if name == "emacs"));
+        assert!(matches!(error("[keys]\nteleport = \"t\""), KeyBindingsError::UnknownAction(name) if name == "teleport"));
+        assert!(matches!(error("[keys]\nundo = \"hyper-z\""), KeyBindingsError::InvalidKey(name) if name == "hyper-z"));
+        for config in ["undo = \"z\"", "[mouse]", "[keys]\nundo", "[keys]\nundo = z", "preset = [\"vim\", \"standard\"]"] {
+            assert!(matches!(error(config), KeyBindingsError::Malformed(_)), "{config}");
+        }
+    }
+
+    #[test]
+    fn keys_bound_to_two_actions_are_rejected() {
+        assert!(matches!(
+            KeyBindings::from_config_str("[keys]\nhint = \"z\""),
+            Err(KeyBindingsError::Conflict(bound_chord, Command::Undo, Command::Hint)) if bound_chord == chord("z")
+        ));
+        // Moving the action that held the key elsewhere resolves the conflict
+        assert!(KeyBindings::from_config_str("[keys]\nhint = \"z\"\nundo = \"u\"").is_ok());
+    }
+
+    #[test]
+    fn presets_have_no_conflicts() {
+        for preset in Preset::ALL {
+            assert!(KeyBindings::from_preset(preset).check_conflicts().is_ok(), "{}", preset.name());
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! A `FreeCell` game engine, with a terminal user interface
+//!
+//! The [`game::Game`] type holds a position and its history. It can deal numbered or seeded games,
+//! list the legal moves, apply and undo moves, and tell when a game is won or stuck. The [`game::solver`] module
+//! searches positions for a win, and [`game::save`] stores games on disk.
+//!
+//! Terminal rendering is in `game::print` and key bindings are in `keys`, both behind the `tui` feature,
+//! which is enabled by default.
+//! To use the engine without pulling in crossterm, depend on this crate with `default-features = false`.
+
+#![warn(
+    missing_docs,
+    clippy::all,
+    clippy::pedantic,
+    clippy::missing_docs_in_private_items
+)]
+
+pub mod cards;
+pub mod game;
+#[cfg(feature = "tui")]
+pub mod keys;
@@ -7,86 +7,571 @@
     clippy::missing_docs_in_private_items
 )]
 
-mod game;
-mod cards;
-
-use crate::game::Game;
+use rusty_freecell::{
+    game::{
+        print::{screen::Screen, MIN_TERMINAL_HEIGHT, MIN_TERMINAL_WIDTH}, save::{self, LoadError}, solver::{self, Solution, SolverLimits},
+        stats::{self, Stats}, Deal, Game, MoveCounting, FOUNDATIONS, FREE_CELLS, MAX_DEAL_NUMBER
+    },
+    keys::{self, Command, KeyBindings, KeyBindingsError, KeyChord}
+};
 
-use std::io::{self, stdout};
+use std::{io::{self, stdout}, path::{Path, PathBuf}, time::{Duration, Instant}};
 
 use crossterm::{
-    cursor, terminal, ExecutableCommand
+    cursor, event::{self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind}, terminal, ExecutableCommand
 };
 
-/// Minimum width of the terminal window supported by the game.
-const MIN_TERMINAL_WIDTH: u16 = 60;
-/// Minimum height of the terminal window supported by the game.
-const MIN_TERMINAL_HEIGHT: u16 = 24;
+/// How long `--solve` searches for a solution before giving up.
+const SOLVE_TIME_BUDGET: Duration = Duration::from_secs(60);
+/// How soon a second click on the same stack has to follow the first to count as a double-click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+/// The name of the file the moves of the current game are exported to, inside the game's data directory.
+const MOVES_FILE_NAME: &str = "moves.txt";
+/// The name of the file the current position is exported to, inside the game's data directory.
+const BOARD_FILE_NAME: &str = "board.txt";
+
+/// Options the game can be launched with from the command line.
+#[derive(Default)]
+struct LaunchOptions {
+    /// The deal to start with, from `--deal <n>` or `--seed <n>`.
+    deal: Option<Deal>,
+
+    /// Whether to check if the deal can be won instead of playing it, from `--solve`.
+    solve: bool,
+
+    /// How new games count moves, one per action with `--count-actions`, otherwise one per card.
+    move_counting: MoveCounting,
+
+    /// A file holding the position to start with in the fc-solve board layout, from `--board <file>`.
+    board: Option<PathBuf>,
+
+    /// Moves in standard notation to play on the deal or board before handing it to the player, from `--replay <moves>`.
+    replay: Option<String>,
+}
+
+/// What the player is doing with the mouse, kept between mouse events.
+#[derive(Default)]
+struct MouseState {
+    /// The stack the left button was pressed on while it is held down,
+    /// and whether releasing it there deselects the stack because it was already selected.
+    press_opt: Option<(usize, bool)>,
+
+    /// The stack last selected with a click and when, to tell a double-click on it.
+    last_click_opt: Option<(usize, Instant)>,
+}
+
+/// A screen shown over the board until the next key press.
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+enum Overlay {
+    /// Nothing is shown over the board.
+    #[default]
+    None,
+    /// The help screen listing the key bindings.
+    Help,
+    /// The statistics screen.
+    Stats,
+    /// The pause screen, which hides the board while the clock is stopped.
+    Paused,
+}
+
+/// The state of the game loop that outlasts a single game.
+struct Session {
+    /// The screen shown over the board, if any.
+    overlay: Overlay,
+
+    /// The statistics of all games played, saved whenever a result is added.
+    stats: Stats,
+
+    /// Whether the result of the current game is already in the statistics, so that it is counted only once.
+    result_recorded: bool,
+}
+
+/// Parses the command line arguments into `LaunchOptions`.
+///
+/// # Errors
+///
+/// Returns an `Err` describing the problem if an argument is unknown or malformed.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<LaunchOptions, Box<dyn std::error::Error>> {
+    let mut options = LaunchOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--deal" => {
+                let deal_number = args.next()
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .filter(|deal_number| (1..=MAX_DEAL_NUMBER).contains(deal_number))
+                    .ok_or(format!("--deal expects a deal number from 1 to {MAX_DEAL_NUMBER}"))?;
+                options.deal = Some(Deal::Numbered(deal_number));
+            },
+            "--seed" => {
+                let seed = args.next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or("--seed expects a number from 0 to 18446744073709551615")?;
+                options.deal = Some(Deal::Seeded(seed));
+            },
+            "--solve" => {
+                options.solve = true;
+            },
+            "--count-actions" => {
+                options.move_counting = MoveCounting::EachAction;
+            },
+            "--board" => {
+                options.board = Some(args.next().ok_or("--board expects the path of a board file")?.into());
+            },
+            "--replay" => {
+                options.replay = Some(args.next().ok_or("--replay expects a list of moves, such as \"3a 4h 18\"")?);
+            },
+            _ => {
+                return Err(format!("unknown argument: {arg}").into());
+            }
+        }
+    }
+    if options.deal.is_some() && options.board.is_some() {
+        return Err("--board cannot be combined with --deal or --seed".into());
+    }
+    if options.replay.is_some() && options.deal.is_none() && options.board.is_none() {
+        return Err("--replay needs the deal to replay the moves on, from --deal, --seed or --board".into());
+    }
+    Ok(options)
+}
+
+/// Picks a random seed for a new deal.
+/// Seeds are kept to 32 bits so they are short enough to read off the title bar and share.
+fn new_seed(rng: &mut impl rand::Rng) -> u64 {
+    u64::from(rng.gen::<u32>())
+}
+
+/// Runs the solver on a game and prints whether it can be won.
+fn print_solvability(game: &Game) {
+    let limits = SolverLimits {
+        time_budget: Some(SOLVE_TIME_BUDGET),
+        ..SolverLimits::default()
+    };
+    let name = game.deal().map_or("The position".to_owned(), |deal| deal.to_string());
+    match solver::solve(game, &limits) {
+        Solution::Solvable(moves) => println!("{name} is solvable in {} moves.", moves.len()),
+        Solution::Unsolvable => println!("{name} is unsolvable."),
+        Solution::Unknown => println!("{name} could not be solved within {} seconds.", SOLVE_TIME_BUDGET.as_secs())
+    }
+}
+
+/// Deals a new game with the launch options applied to it.
+fn new_game(deal: Deal, options: &LaunchOptions) -> Game {
+    let mut game = Game::from_deal(deal);
+    game.set_move_counting(options.move_counting);
+    game
+}
+
+/// Replaces the game with a new one dealt from a deal, keeping the auto-play and high contrast modes the player set.
+fn replace_game(game: &mut Game, deal: Deal, options: &LaunchOptions) {
+    let mut next_game = new_game(deal, options);
+    next_game.copy_modes_from(game);
+    *game = next_game;
+}
+
+/// Sets up the game to start with, from the board given with `--board` or else from the deal,
+/// and plays the moves given with `--replay` on it.
+///
+/// # Errors
+///
+/// Returns an `Err` explaining what is wrong if the board cannot be loaded or a move cannot be replayed.
+fn first_game(options: &LaunchOptions) -> Result<Game, Box<dyn std::error::Error>> {
+    let mut game = if let Some(board_path) = &options.board {
+        let mut game = Game::load_board_file(board_path).map_err(|error| {
+            println!("Could not load the board at {}: {error}.", board_path.display());
+            "invalid board file"
+        })?;
+        game.set_move_counting(options.move_counting);
+        game
+    } else {
+        new_game(options.deal.unwrap_or_else(|| Deal::Seeded(new_seed(&mut rand::thread_rng()))), options)
+    };
+    if let Some(moves) = &options.replay {
+        if let Err(error) = game.replay(moves) {
+            println!("Could not replay the moves: {error}.");
+            return Err("invalid moves".into());
+        }
+    }
+    Ok(game)
+}
+
+/// Writes the moves of the current game, already in standard notation, to a file in the game's data directory,
+/// headed by a comment naming the deal, so the file can be passed back with `--replay "$(cat moves.txt)"`.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if the file or its directory cannot be written.
+fn export_moves(game: &Game, move_notation: String) -> Result<(), io::Error> {
+    let data_dir_path = save::data_dir_path().ok_or(io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let mut lines: Vec<String> = game.deal().map(|deal| format!("# {deal}")).into_iter().collect();
+    lines.push(move_notation);
+    std::fs::create_dir_all(&data_dir_path)?;
+    std::fs::write(data_dir_path.join(MOVES_FILE_NAME), lines.join("\n") + "\n")
+}
+
+/// Writes the current position in the fc-solve board layout to a file in the game's data directory,
+/// so it can be fed to other solvers or passed back with `--board`.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if the file or its directory cannot be written.
+fn export_board(game: &Game) -> Result<(), io::Error> {
+    let data_dir_path = save::data_dir_path().ok_or(io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    std::fs::create_dir_all(&data_dir_path)?;
+    std::fs::write(data_dir_path.join(BOARD_FILE_NAME), game.board_string())
+}
+
+/// Loads the game saved when the player last quit, if there is one.
+/// A save file that cannot be loaded is set aside with a warning, so that a new game starts instead.
+fn load_saved_game(warnings: &mut Vec<String>) -> Option<Game> {
+    let save_file_path = save::save_file_path()?;
+    match Game::load_from_file(&save_file_path) {
+        Ok(game) => Some(game),
+        Err(LoadError::Io(error)) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            set_aside_unreadable_file(&save_file_path, &format!("the saved game: {error}"));
+            warnings.push("Could not load the saved game, it was set aside".to_owned());
+            None
+        }
+    }
+}
+
+/// Renames a file that could not be loaded to end in ".bad", so that writing a new one does not lose it,
+/// and explains what could not be loaded and why before the game starts.
+fn set_aside_unreadable_file(path: &Path, what: &str) {
+    let mut bad_file_name = path.file_name().unwrap_or_default().to_owned();
+    bad_file_name.push(".bad");
+    let bad_file_path = path.with_file_name(bad_file_name);
+    match std::fs::rename(path, &bad_file_path) {
+        Ok(()) => println!("Could not load {what}. The file was moved to {}.", bad_file_path.display()),
+        Err(rename_error) => println!("Could not load {what}. The file could not be moved aside either: {rename_error}.")
+    }
+}
+
+/// Loads the player's key bindings from the config file, or the default key bindings if there is no config file.
+///
+/// # Errors
+///
+/// Returns an `Err` explaining what is wrong if the config file exists but its key bindings are invalid.
+fn load_key_bindings() -> Result<KeyBindings, Box<dyn std::error::Error>> {
+    let Some(config_file_path) = keys::config_file_path() else {return Ok(KeyBindings::default())};
+    match KeyBindings::load_config_file(&config_file_path) {
+        Ok(bindings) => Ok(bindings),
+        Err(KeyBindingsError::Io(error)) if error.kind() == io::ErrorKind::NotFound => Ok(KeyBindings::default()),
+        Err(error) => {
+            println!("Could not load the key bindings at {}: {error}.", config_file_path.display());
+            Err("invalid key bindings file".into())
+        }
+    }
+}
+
+/// Loads the statistics of the games played so far, or empty statistics if there are none yet.
+/// A statistics file that cannot be loaded is set aside with a warning, so that the statistics start over.
+fn load_stats(warnings: &mut Vec<String>) -> Stats {
+    let Some(stats_file_path) = stats::stats_file_path() else {return Stats::default()};
+    match Stats::load_from_file(&stats_file_path) {
+        Ok(stats) => stats,
+        Err(LoadError::Io(error)) if error.kind() == io::ErrorKind::NotFound => Stats::default(),
+        Err(error) => {
+            set_aside_unreadable_file(&stats_file_path, &format!("the statistics: {error}"));
+            warnings.push("Could not load the statistics, they were set aside".to_owned());
+            Stats::default()
+        }
+    }
+}
+
+/// Adds the result of the current game to the statistics and saves them, unless it was added already.
+/// A game that is not won when its result is recorded counts as a loss, but one without a single move is not counted at all.
+fn record_result(game: &mut Game, session: &mut Session) {
+    if session.result_recorded || !session.stats.record_game(game) {return;}
+    session.result_recorded = true;
+    let Some(stats_file_path) = stats::stats_file_path() else {return};
+    if let Err(error) = session.stats.save_to_file(&stats_file_path) {
+        game.set_status_message(format!("Could not save the statistics: {error}"));
+    }
+}
+
+/// Saves an unfinished game so it can be resumed on the next launch,
+/// or removes the save file if the game was won or has no moves to resume.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if the save file cannot be written or removed.
+fn autosave(game: &Game) -> Result<(), io::Error> {
+    let Some(save_file_path) = save::save_file_path() else {return Ok(())};
+    if game.is_won() || game.move_count() == 0 {
+        match std::fs::remove_file(save_file_path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(())
+        }
+    } else {
+        game.save_to_file(&save_file_path)
+    }
+}
+
+/// Handles a key press by running the action bound to it, see the controls in the README.
+/// While a screen is shown over the board, any key closes it instead.
+///
+/// # Returns
+///
+/// `true` if the player asked to quit, otherwise `false`.
+fn handle_key_event(game: &mut Game, key_event: KeyEvent, bindings: &KeyBindings, options: &LaunchOptions, rng: &mut impl rand::Rng, session: &mut Session) -> bool {
+    if key_event.kind != KeyEventKind::Press && key_event.kind != KeyEventKind::Repeat {return false;}
+    // A status message stays up until the next key press at the latest
+    game.clear_status_message();
+    if session.overlay != Overlay::None {
+        if session.overlay == Overlay::Paused {
+            game.resume_clock();
+        }
+        session.overlay = Overlay::None;
+        return false;
+    }
+    let Some(command) = bindings.command_for(KeyChord::from(key_event)) else {return false};
+    match command {
+        Command::CursorLeft if !game.is_won() => {
+            game.move_cursor_left();
+        },
+        Command::CursorRight if !game.is_won() => {
+            game.move_cursor_right();
+        },
+        Command::CursorUp if !game.is_won() => {
+            game.move_cursor_up();
+        },
+        Command::CursorDown if !game.is_won() => {
+            game.move_cursor_down();
+        },
+        Command::Select if !game.is_won() => {
+            game.handle_card_press();
+        },
+        Command::Column(column) if !game.is_won() => {
+            game.handle_stack_press(FOUNDATIONS + FREE_CELLS + column);
+        },
+        Command::FreeCell(free_cell) if !game.is_won() => {
+            game.handle_stack_press(FOUNDATIONS + free_cell);
+        },
+        Command::Foundation if !game.is_won() => {
+            game.handle_foundation_press();
+        },
+        Command::Send if !game.is_won() => {
+            // A failed send explains itself in the status line
+            let _ = game.smart_send();
+        },
+        Command::Undo => {
+            game.perform_undo();
+        },
+        Command::Redo => {
+            game.perform_redo();
+        },
+        Command::HighContrast => {
+            game.toggle_high_contrast();
+        },
+        Command::AutoPlay => {
+            game.toggle_auto_play();
+        },
+        Command::QuickStack => {
+            game.quick_stack_to_foundations();
+        },
+        Command::Hint if !game.is_won() => {
+            game.show_hint();
+        },
+        Command::NewGame => {
+            // Walking away from an unfinished game counts as a loss
+            record_result(game, session);
+            replace_game(game, Deal::Seeded(new_seed(rng)), options);
+            session.result_recorded = false;
+        },
+        Command::ExportMoves => {
+            let message = match game.move_notation() {
+                Ok(move_notation) => match export_moves(game, move_notation) {
+                    Ok(()) => format!("Exported the moves to {MOVES_FILE_NAME}"),
+                    Err(error) => format!("Could not export the moves: {error}")
+                },
+                Err(error) => format!("Cannot export: {error}")
+            };
+            game.set_status_message(message);
+        },
+        Command::ExportBoard => {
+            match export_board(game) {
+                Ok(()) => game.set_status_message(format!("Exported the board to {BOARD_FILE_NAME}")),
+                Err(error) => game.set_status_message(format!("Could not export the board: {error}"))
+            }
+        },
+        Command::Restart => {
+            if let Some(deal) = game.deal() {
+                // Starting the deal over counts as a loss, like walking away from it
+                record_result(game, session);
+                replace_game(game, deal, options);
+                session.result_recorded = false;
+            }
+        },
+        Command::Help => {
+            session.overlay = Overlay::Help;
+        },
+        Command::Stats => {
+            session.overlay = Overlay::Stats;
+        },
+        Command::Pause if !game.is_won() => {
+            game.pause_clock();
+            session.overlay = Overlay::Paused;
+        },
+        Command::Quit => {
+            return true;
+        },
+        _ => {}
+    }
+    false
+}
+
+/// Handles a mouse event. Clicking a stack selects it, clicking another stack moves the selected cards there,
+/// dragging cards from one stack and dropping them on another moves them, and double-clicking a stack sends its top card
+/// to its foundation or to a free cell. The height of the terminal tells where the bottom bar is, which is not part of any stack.
+fn handle_mouse_event(game: &mut Game, mouse: &mut MouseState, mouse_event: MouseEvent, term_height: u16) {
+    let stack_opt = Game::stack_at_coord(mouse_event.column, mouse_event.row, term_height);
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            game.clear_status_message();
+            let Some(stack) = stack_opt else {return};
+            let now = Instant::now();
+            let is_double_click = mouse.last_click_opt.take()
+                .is_some_and(|(last_stack, time)| last_stack == stack && now - time < DOUBLE_CLICK_TIME);
+            if is_double_click && game.selected_stack() == Some(stack) {
+                mouse.press_opt = None;
+                // A failed send explains itself in the status line
+                let _ = game.send_to_foundation_or_free_cell(stack);
+            } else if game.selected_stack() == Some(stack) {
+                mouse.press_opt = Some((stack, true));
+            } else {
+                game.handle_stack_press(stack);
+                if game.selected_stack() == Some(stack) {
+                    mouse.press_opt = Some((stack, false));
+                    mouse.last_click_opt = Some((stack, now));
+                }
+            }
+        },
+        MouseEventKind::Drag(MouseButton::Left) => {
+            if let (Some(_), Some(stack)) = (mouse.press_opt, stack_opt) {
+                game.highlight_stack(stack);
+            }
+        },
+        MouseEventKind::Up(MouseButton::Left) => {
+            let Some((pressed_stack, deselect)) = mouse.press_opt.take() else {return};
+            match stack_opt {
+                // Dropped on another stack
+                Some(stack) if stack != pressed_stack && game.selected_stack() == Some(pressed_stack) => {
+                    game.handle_stack_press(stack);
+                },
+                // Clicked an already selected stack
+                Some(stack) if stack == pressed_stack && deselect => {
+                    game.handle_stack_press(stack);
+                },
+                _ => {}
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Waits for the player to answer a yes or no question with y/enter or n/escape.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if there is an issue with terminal I/O.
+fn read_yes_or_no() -> Result<bool, io::Error> {
+    loop {
+        if let Event::Key(key_event) = event::read()? {
+            if key_event.kind == KeyEventKind::Press {
+                match key_event.code {
+                    KeyCode::Char('y') | KeyCode::Enter => return Ok(true),
+                    KeyCode::Char('n') | KeyCode::Esc => return Ok(false),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
 
 /// Runs the game loop.
 ///
 /// # Errors
 ///
 /// Returns an `io::Error` if there is an issue with terminal I/O.
-fn run() -> Result<(), io::Error> {
+fn run(options: &LaunchOptions, bindings: &KeyBindings, stats: Stats, first_game: Game, saved_game: Option<Game>, warnings: &[String]) -> Result<(), io::Error> {
     // Prepare terminal
     terminal::enable_raw_mode()?;
     let mut stdout = stdout();
     stdout.execute(terminal::EnterAlternateScreen)?;
     stdout.execute(cursor::Hide)?;
+    stdout.execute(event::EnableMouseCapture)?;
     stdout.execute(terminal::Clear(terminal::ClearType::All))?;
+    let (term_width, term_height) = terminal::size()?;
+    let mut screen = Screen::new(stdout, term_width, term_height);
 
     // Create game
     let mut rng = rand::thread_rng();
-    let mut game = Game::new(&mut rng);
-    game.print(&mut stdout)?;
+    let mut game = first_game;
+    let mut session = Session {
+        overlay: Overlay::None,
+        stats,
+        result_recorded: false,
+    };
+    if let Some(mut saved_game) = saved_game {
+        saved_game.print_resume_prompt(&mut screen, bindings)?;
+        if read_yes_or_no()? {
+            game = saved_game;
+        } else {
+            // Abandoning the saved game counts as a loss
+            record_result(&mut saved_game, &mut session);
+            session.result_recorded = false;
+            if let Some(message) = saved_game.status_message() {
+                game.set_status_message(message.to_owned());
+            }
+        }
+    }
+    if let Some(warning) = warnings.first() {
+        game.set_status_message(warning.clone());
+    }
+    game.print(&mut screen, bindings)?;
+    let mut mouse = MouseState::default();
 
     // Game loop
     loop {
-        let event = crossterm::event::read()?;
-        match event {
-            crossterm::event::Event::Key(key_event) => {
-                use crossterm::event::{KeyModifiers as MOD, KeyCode::{Char, Left, Right, Enter}, KeyEventKind::{Press, Repeat}};
-                if key_event.kind == Press || key_event.kind == Repeat {
-                    match (key_event.code, key_event.modifiers) {
-                        (Left | Char('a'), MOD::NONE) => {
-                            if !game.is_won() {game.move_cursor_left();}
-                        },
-                        (Right | Char('d') , MOD::NONE) => {
-                            if !game.is_won() {game.move_cursor_right();}
-                        },
-                        (Char(' ') | Enter, MOD::NONE) => {
-                            if !game.is_won() {game.handle_card_press();}
-                        },
-                        (Char('z'), MOD::NONE) => {
-                            game.perform_undo();
-                        },
-                        (Char('h'), MOD::NONE) => {
-                            game.toggle_high_contrast();
-                        },
-                        (Char('f'), MOD::NONE) => {
-                            game.quick_stack_to_foundations();
-                        },
-                        (Char('n'), MOD::CONTROL) => {
-                            game = Game::new(&mut rng);
-                        },
-                        (Char('q'), MOD::CONTROL) => {
-                            break
-                        },
-                        _ => {
-                            
-                        }
-                    }
+        // Wait for the next event, or until the game changes by itself and needs to be printed again.
+        // With nothing due to change, wait for the next event only, so that an idle game takes no CPU time.
+        let event_opt = match game.next_tick() {
+            Some(next_tick) if !event::poll(next_tick.saturating_duration_since(Instant::now()))? => None,
+            _ => Some(event::read()?)
+        };
+        game.tick();
+        match event_opt {
+            Some(crossterm::event::Event::Key(key_event)) => {
+                let quit = handle_key_event(&mut game, key_event, bindings, options, &mut rng, &mut session);
+                if quit {break;}
+            },
+            Some(crossterm::event::Event::Mouse(mouse_event)) => {
+                // Plain mouse movement changes nothing, so it is not worth printing the game again
+                if mouse_event.kind == MouseEventKind::Moved {continue;}
+                if !game.is_won() && session.overlay == Overlay::None {
+                    handle_mouse_event(&mut game, &mut mouse, mouse_event, screen.height());
                 }
             },
-            crossterm::event::Event::Resize(_term_width, _term_height) => {
-                // Resize event falls through and triggers game to print again
+            Some(crossterm::event::Event::Resize(term_width, term_height)) => {
+                // Resize event falls through and triggers game to print again, in full
+                screen.resize(term_width, term_height);
+            },
+            _ => {
+                // A clock tick or any other event falls through and triggers game to print again
             }
-            _ => {}
         }
-        game.print(&mut stdout)?;
+        if game.is_won() {
+            record_result(&mut game, &mut session);
+        }
+        match session.overlay {
+            Overlay::None => game.print(&mut screen, bindings)?,
+            Overlay::Help => game.print_help(&mut screen, bindings)?,
+            Overlay::Stats => game.print_stats(&mut screen, bindings, &session.stats)?,
+            Overlay::Paused => game.print_paused(&mut screen, bindings)?
+        }
     }
-    Ok(())
+    autosave(&game)
 }
 
 /// Cleans up the terminal after the game finishes or is interrupted.
@@ -94,6 +579,7 @@ fn run() -> Result<(), io::Error> {
 fn cleanup() {
     let mut stdout = stdout();
     // Do not catch errors here. By the time we cleanup, we want to execute as many of these as possible to reset the terminal.
+    let _ = stdout.execute(event::DisableMouseCapture);
     let _ = stdout.execute(cursor::Show);
     let _ = terminal::disable_raw_mode();
     let _ = stdout.execute(terminal::Clear(terminal::ClearType::All));
@@ -105,15 +591,26 @@ fn cleanup() {
 ///
 /// # Errors
 ///
-/// Returns an `Err` if the terminal window is too small to play the game.
+/// Returns an `Err` if the command line arguments or key bindings are invalid, or the terminal window is too small to play the game.
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     //std::env::set_var("RUST_BACKTRACE", "1");
+    let options = parse_args(std::env::args().skip(1))?;
+    let game = first_game(&options)?;
+    if options.solve {
+        print_solvability(&game);
+        return Ok(());
+    }
+    let bindings = load_key_bindings()?;
+    let mut warnings = Vec::new();
+    let stats = load_stats(&mut warnings);
     let (term_width, term_height) = terminal::size()?;
     if term_width < MIN_TERMINAL_WIDTH || term_height < MIN_TERMINAL_HEIGHT {
         println!("Your terminal window is too small for FreeCell! It's gotta be at least {MIN_TERMINAL_WIDTH} chars wide and {MIN_TERMINAL_HEIGHT} chars tall.");
         return Err("terminal too small".into());
     }
-    run()?;
+    // The saved game is offered even when the player asked for a specific deal or board, so that it is not overwritten uncounted
+    let saved_game = load_saved_game(&mut warnings);
+    let result = run(&options, &bindings, stats, game, saved_game, &warnings);
     cleanup();
-    Ok(())
+    Ok(result?)
 }